@@ -11,19 +11,98 @@ mod day_10;
 mod day_11;
 mod day_12;
 
-pub fn run(day: u8) {
+use std::{fs::File, io::BufWriter, path::PathBuf};
+
+use clap::Args;
+
+/// Optional, day-specific extras. Days that don't support a given option ignore it.
+#[derive(Args, Debug, Default)]
+pub struct Options {
+    /// Print a detailed breakdown of how the answers were reached (stdout only, not written to
+    /// the output file).
+    #[arg(long, global = true)]
+    pub report: bool,
+
+    /// Export extra data about the solution to a file. The format is chosen by the file
+    /// extension, and which formats are available depends on the day.
+    #[arg(long, value_name = "FILE", global = true)]
+    pub export: Option<PathBuf>,
+
+    /// Double-check the answers where possible, panicking if they turn out to be wrong.
+    #[arg(long, global = true)]
+    pub verify: bool,
+
+    /// Day 8: how many of the closest pairs of junction boxes to connect in part 1 [default:
+    /// 1000].
+    #[arg(long, value_name = "N", global = true)]
+    pub pairs: Option<usize>,
+
+    /// Day 8: how many of the largest circuits to multiply together in part 1 [default: 3].
+    #[arg(long, value_name = "K", global = true)]
+    pub top: Option<usize>,
+
+    /// Day 11: list up to this many paths for each part (or just for the query, with the query
+    /// subcommand), in lexicographic order.
+    #[arg(long, value_name = "N", global = true)]
+    pub list_paths: Option<usize>,
+
+    /// Day 11: pick this many paths for each part (or just for the query, with the query
+    /// subcommand) at random, each path being equally likely.
+    #[arg(long, value_name = "N", global = true)]
+    pub sample_paths: Option<usize>,
+
+    /// Seed for anything random, to make it repeatable.
+    #[arg(long, global = true)]
+    pub seed: Option<u64>,
+
+    /// Set by the query subcommand, rather than directly on the command line.
+    #[arg(skip)]
+    pub query: Option<QueryArgs>,
+}
+
+/// A question about a day's input. Only day 11 supports these so far.
+#[derive(Args, Debug, Default)]
+pub struct QueryArgs {
+    /// Day 11: the device the paths start from.
+    #[arg(long, value_name = "DEVICE")]
+    pub from: String,
+
+    /// Day 11: the device the paths end at.
+    #[arg(long, value_name = "DEVICE")]
+    pub to: String,
+
+    /// Day 11: devices the paths have to pass through (in any order), separated by commas.
+    #[arg(long, value_name = "DEVICES", value_delimiter = ',')]
+    pub via: Vec<String>,
+}
+
+impl Options {
+    /// Creates the --export file, if one was requested, returning a writer for it along with the
+    /// requested format (the file extension).
+    pub fn create_export(&self) -> Option<(BufWriter<File>, &str)> {
+        let path = self.export.as_ref()?;
+        let format = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        Some((BufWriter::new(File::create(path).unwrap()), format))
+    }
+}
+
+pub fn run(day: u8, options: &Options) {
+    if options.query.is_some() && day != 11 {
+        panic!("day {} doesn't support queries", day);
+    }
+
     match day {
         1 => day_01::run(),
         2 => day_02::run(),
         3 => day_03::run(),
         4 => day_04::run(),
         5 => day_05::run(),
-        6 => day_06::run(),
-        7 => day_07::run(),
-        8 => day_08::run(),
+        6 => day_06::run(options),
+        7 => day_07::run(options),
+        8 => day_08::run(options),
         9 => day_09::run(),
-        10 => day_10::run(),
-        11 => day_11::run(),
+        10 => day_10::run(options),
+        11 => day_11::run(options),
         12 => day_12::run(),
         0 | 13..=u8::MAX => panic!("invalid day"),
     };
@@ -4,6 +4,7 @@ use std::{
 };
 
 use aoclib_rs::{prep_io, printwriteln};
+use tracing::{debug, trace};
 
 pub fn run() {
     let mut contents = String::new();
@@ -19,7 +20,7 @@ pub fn run() {
         ));
     }
 
-    println!("{:?}", ranges);
+    debug!("ranges: {:?}", ranges);
 
     part1(&mut writer, &ranges);
     part2(&mut writer, &ranges);
@@ -78,7 +79,7 @@ fn generate_patterns(
     endi: i64,
     seen: &mut HashSet<String>,
 ) -> i64 {
-    if current.len() % divisor != 0 {
+    if !current.len().is_multiple_of(divisor) {
         return 0;
     }
     let part = current[..(current.len() / divisor)].to_string();
@@ -88,7 +89,7 @@ fn generate_patterns(
     }
     let mut total = 0;
     if geq(&rep, starti) && leq(&rep, endi) && !seen.contains(&rep) {
-        println!("{}", &rep);
+        trace!("invalid ID: {}", &rep);
         total += rep.parse::<i64>().unwrap();
         seen.insert(rep);
     }
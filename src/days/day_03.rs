@@ -1,6 +1,7 @@
 use std::io::{BufWriter, Write};
 
 use aoclib_rs::{prep_io, printwriteln};
+use tracing::debug;
 
 pub fn run() {
     let mut contents = String::new();
@@ -40,7 +41,7 @@ fn max_for_length(banks: &Vec<Vec<u32>>, length: usize) -> u64 {
             num = num + &format!("{}", digit);
         }
 
-        println!("{}", num);
+        debug!("largest joltage: {}", num);
 
         total += num.parse::<u64>().unwrap();
     }
@@ -1,13 +1,29 @@
-use std::io::{BufWriter, Write};
+use std::{
+    fmt,
+    fmt::Formatter,
+    io::{BufWriter, Write},
+    ops::Range,
+};
 
 use aoclib_rs::{prep_io, printwriteln};
 
+use super::Options;
+
 #[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
 enum Op {
     Add,
     Multiply,
 }
 
+impl Op {
+    fn apply(&self, nums: &[i64]) -> i64 {
+        match self {
+            Op::Add => nums.iter().sum(),
+            Op::Multiply => nums.iter().product(),
+        }
+    }
+}
+
 impl From<&str> for Op {
     fn from(s: &str) -> Self {
         match s.chars().next().unwrap() {
@@ -18,98 +34,114 @@ impl From<&str> for Op {
     }
 }
 
-pub fn run() {
-    let mut contents = String::new();
-    let (mut writer, contents) = prep_io(&mut contents, 6).unwrap();
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Op::Add => write!(f, "+"),
+            Op::Multiply => write!(f, "*"),
+        }
+    }
+}
+
+// A single problem on the worksheet: a block of columns bounded on either side by all-blank
+// columns (or the edge of the sheet), read both the human way and the cephalopod way.
+#[derive(Clone, Debug)]
+struct Problem {
+    cols: Range<usize>,
+
+    // numbers read left-to-right along each row (part 1)
+    row_nums: Vec<i64>,
+
+    // numbers read top-to-bottom down each column (part 2)
+    col_nums: Vec<i64>,
 
-    part1(&mut writer, &contents);
-    part2(&mut writer, &contents);
+    op: Op,
 }
 
-fn part1<W: Write>(writer: &mut BufWriter<W>, contents: &Vec<&str>) {
-    let mut nums: Vec<Vec<i64>> = Vec::new();
-    let mut ops: Vec<Op> = Vec::new();
-    for (i, line) in contents.iter().enumerate() {
-        let line_split = line.split(" ");
-        let line_split = line_split.filter(|l| !l.is_empty());
-
-        if i < contents.len() - 1 {
-            let mut nv: Vec<i64> = Vec::new();
-            nv.extend(line_split.map(|i| i.parse::<i64>().unwrap()));
-            nums.push(nv);
-        } else {
-            ops.extend(line_split.map(Op::from));
-        }
+impl Problem {
+    fn row_subtotal(&self) -> i64 {
+        self.op.apply(&self.row_nums)
     }
 
-    let total = solve(&nums, &ops);
-    printwriteln!(writer, "{}", total).unwrap();
+    fn col_subtotal(&self) -> i64 {
+        self.op.apply(&self.col_nums)
+    }
 }
 
-fn solve(nums: &Vec<Vec<i64>>, ops: &[Op]) -> i64 {
-    let mut total = 0;
-    for i in 0..nums[0].len() {
-        let mut subtotal = if ops[i] == Op::Add { 0 } else { 1 };
-        for row in nums {
-            if ops[i] == Op::Add {
-                subtotal += row[i];
-            } else {
-                subtotal *= row[i];
-            }
-        }
-        total += subtotal;
+pub fn run(options: &Options) {
+    let mut contents = String::new();
+    let (mut writer, contents) = prep_io(&mut contents, 6).unwrap();
+    let problems = parse_problems(&contents);
+
+    part1(&mut writer, &problems);
+    part2(&mut writer, &problems);
+
+    if options.report {
+        print_report(&problems);
     }
-    total
 }
 
-fn part2<W: Write>(writer: &mut BufWriter<W>, contents: &[&str]) {
-    let num_chars: Vec<Vec<char>> = contents[..(contents.len() - 1)]
+fn parse_problems(contents: &[&str]) -> Vec<Problem> {
+    // pad every line out to the same width, since trailing spaces may have been trimmed
+    let width = contents
+        .iter()
+        .map(|line| line.chars().count())
+        .max()
+        .unwrap();
+    let grid: Vec<Vec<char>> = contents
         .iter()
-        .map(|line| line.chars().collect())
+        .map(|line| {
+            let mut chars: Vec<char> = line.chars().collect();
+            chars.resize(width, ' ');
+            chars
+        })
         .collect();
-
-    let mut ops: Vec<Op> = Vec::new();
-    let op_line_split = contents[contents.len() - 1].split(" ");
-    let op_line_split = op_line_split.filter(|l| !l.is_empty());
-    ops.extend(op_line_split.map(Op::from));
-
-    let mut nums: Vec<Vec<i64>> = Vec::new();
-    let mut start_new_problem = true;
-    for col in 0..num_chars[0].len() {
-        if col_is_empty(&num_chars, col) {
-            start_new_problem = true;
-            continue;
-        }
-
-        let mut num_str = "".to_string();
-        for line in &num_chars {
-            if line[col] != ' ' {
-                num_str = num_str + &line[col].to_string();
+    let (num_chars, op_line) = grid.split_at(grid.len() - 1);
+    let op_line = &op_line[0];
+
+    let mut problems = Vec::new();
+    let mut start = None;
+    for col in 0..=width {
+        if col == width || col_is_empty(num_chars, col) {
+            if let Some(start) = start.take() {
+                problems.push(parse_problem(num_chars, op_line, start..col));
             }
+        } else if start.is_none() {
+            start = Some(col);
         }
-        let num: i64 = num_str.parse().unwrap();
-        if start_new_problem {
-            nums.push(Vec::new());
-            start_new_problem = false;
-        }
-        let last = nums.len() - 1;
-        nums[last].push(num);
     }
-    println!("{:?}", nums);
+    problems
+}
 
-    if nums.len() != ops.len() {
-        panic!(
-            "length mismatch: {} groups of nums, {} ops",
-            nums.len(),
-            ops.len()
-        );
+fn parse_problem(num_chars: &[Vec<char>], op_line: &[char], cols: Range<usize>) -> Problem {
+    let row_nums = num_chars
+        .iter()
+        .map(|line| parse_num(line[cols.clone()].iter()))
+        .collect();
+    let col_nums = cols
+        .clone()
+        .map(|col| parse_num(num_chars.iter().map(|line| &line[col])))
+        .collect();
+
+    let op_str: String = op_line[cols.clone()]
+        .iter()
+        .filter(|&&c| c != ' ')
+        .collect();
+
+    Problem {
+        cols,
+        row_nums,
+        col_nums,
+        op: Op::from(op_str.as_str()),
     }
+}
 
-    let total = solve2(&nums, &ops);
-    printwriteln!(writer, "{}", total).unwrap();
+fn parse_num<'a>(chars: impl Iterator<Item = &'a char>) -> i64 {
+    let num_str: String = chars.filter(|&&c| c != ' ').collect();
+    num_str.parse().unwrap()
 }
 
-fn col_is_empty(num_chars: &Vec<Vec<char>>, col: usize) -> bool {
+fn col_is_empty(num_chars: &[Vec<char>], col: usize) -> bool {
     for line in num_chars {
         if line[col] != ' ' {
             return false;
@@ -118,18 +150,71 @@ fn col_is_empty(num_chars: &Vec<Vec<char>>, col: usize) -> bool {
     true
 }
 
-fn solve2(nums: &[Vec<i64>], ops: &[Op]) -> i64 {
-    let mut total = 0;
-    for (i, ns) in nums.iter().enumerate() {
-        let mut subtotal = if ops[i] == Op::Add { 0 } else { 1 };
-        for n in ns {
-            if ops[i] == Op::Add {
-                subtotal += n;
-            } else {
-                subtotal *= n;
-            }
-        }
-        total += subtotal;
+fn part1<W: Write>(writer: &mut BufWriter<W>, problems: &[Problem]) {
+    let total: i64 = problems.iter().map(Problem::row_subtotal).sum();
+    printwriteln!(writer, "{}", total).unwrap();
+}
+
+fn part2<W: Write>(writer: &mut BufWriter<W>, problems: &[Problem]) {
+    let total: i64 = problems.iter().map(Problem::col_subtotal).sum();
+    printwriteln!(writer, "{}", total).unwrap();
+}
+
+// Prints one line per problem, with the part 1 (row) and part 2 (column) readings side by side:
+//
+// cols    op  part 1 (rows)         part 2 (columns)
+// 0..3    *   123 45 6   = 33210    1 24 356   = 8544
+// 4..7    +   328 64 98  = 490      369 248 8  = 625
+// ...
+// total       4277556               3263827
+fn print_report(problems: &[Problem]) {
+    let join = |nums: &[i64]| {
+        nums.iter()
+            .map(|n| n.to_string())
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+
+    let lines: Vec<_> = problems
+        .iter()
+        .map(|p| {
+            (
+                format!("{}..{}", p.cols.start, p.cols.end),
+                p.op,
+                join(&p.row_nums),
+                p.row_subtotal(),
+                join(&p.col_nums),
+                p.col_subtotal(),
+            )
+        })
+        .collect();
+
+    let cols_width = lines.iter().map(|l| l.0.len()).max().unwrap_or(0).max(5);
+    let rows_width = lines.iter().map(|l| l.2.len()).max().unwrap_or(0);
+    let row_subtotal_width = lines
+        .iter()
+        .map(|l| l.3.to_string().len())
+        .max()
+        .unwrap_or(0);
+    let cols_nums_width = lines.iter().map(|l| l.4.len()).max().unwrap_or(0);
+
+    let part1_width = rows_width + row_subtotal_width + 3;
+
+    println!(
+        "{:<cols_width$}  op  {:<part1_width$}  part 2 (columns)",
+        "cols", "part 1 (rows)",
+    );
+    for (cols, op, row_nums, row_subtotal, col_nums, col_subtotal) in lines {
+        println!(
+            "{:<cols_width$}  {}   {:<rows_width$} = {:<row_subtotal_width$}  {:<cols_nums_width$} = {}",
+            cols, op, row_nums, row_subtotal, col_nums, col_subtotal,
+        );
     }
-    total
+
+    let row_total: i64 = problems.iter().map(Problem::row_subtotal).sum();
+    let col_total: i64 = problems.iter().map(Problem::col_subtotal).sum();
+    println!(
+        "{:<cols_width$}      {:<part1_width$}  {}",
+        "total", row_total, col_total,
+    );
 }
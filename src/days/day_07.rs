@@ -1,18 +1,57 @@
 use std::{
-    collections::{HashMap, HashSet},
+    collections::HashMap,
     io::{BufWriter, Write},
+    mem,
 };
 
-use aoclib_rs::{
-    dir::{Dir4, Direction},
-    position_2d, prep_io, printwriteln,
-};
+use aoclib_rs::{dir::Dir4, position_2d, prep_io, printwriteln};
+
+use num_bigint::BigUint;
+
+use super::Options;
 
 #[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
 enum Cell {
     Empty,
     Start,
     Splitter,
+    MirrorLeft,
+    MirrorRight,
+    Absorber,
+    EmitterLeft,
+    EmitterRight,
+}
+
+impl Cell {
+    // The directions a beam carries on in after entering this cell from above. Down means it
+    // carries straight on; Left and Right mean it shifts one column over before continuing
+    // downwards from there. An empty slice means the beam stops here.
+    fn exits(&self) -> &'static [Dir4] {
+        match self {
+            Cell::Empty | Cell::Start => &[Dir4::Down],
+            Cell::Splitter => &[Dir4::Left, Dir4::Right],
+            Cell::MirrorLeft => &[Dir4::Left],
+            Cell::MirrorRight => &[Dir4::Right],
+            Cell::Absorber => &[],
+            Cell::EmitterLeft => &[Dir4::Down, Dir4::Left],
+            Cell::EmitterRight => &[Dir4::Down, Dir4::Right],
+        }
+    }
+}
+
+impl From<Cell> for char {
+    fn from(cell: Cell) -> Self {
+        match cell {
+            Cell::Empty => '.',
+            Cell::Start => 'S',
+            Cell::Splitter => '^',
+            Cell::MirrorLeft => '/',
+            Cell::MirrorRight => '\\',
+            Cell::Absorber => '#',
+            Cell::EmitterLeft => '<',
+            Cell::EmitterRight => '>',
+        }
+    }
 }
 
 impl From<char> for Cell {
@@ -21,12 +60,36 @@ impl From<char> for Cell {
             '.' => Cell::Empty,
             'S' => Cell::Start,
             '^' => Cell::Splitter,
+            '/' => Cell::MirrorLeft,
+            '\\' => Cell::MirrorRight,
+            '#' => Cell::Absorber,
+            '<' => Cell::EmitterLeft,
+            '>' => Cell::EmitterRight,
             _ => panic!("invalid input"),
         }
     }
 }
 
-pub fn run() {
+// Whatever a beam carries along with it. When two beams land in the same cell they become one
+// beam, and their payloads are merged.
+trait Payload: Clone {
+    fn merge(&mut self, other: &Self);
+}
+
+// Presence only - merging two beams just leaves one beam.
+impl Payload for () {
+    fn merge(&mut self, _other: &Self) {}
+}
+
+// Number of timelines the beam represents - merging two beams adds their timelines together.
+// Since the number of timelines can double at every splitter, these get big fast.
+impl Payload for BigUint {
+    fn merge(&mut self, other: &Self) {
+        *self += other;
+    }
+}
+
+pub fn run(options: &Options) {
     let mut contents = String::new();
     let (mut writer, contents) = prep_io(&mut contents, 7).unwrap();
     let map: Vec<Vec<_>> = contents
@@ -37,67 +100,180 @@ pub fn run() {
 
     part1(&mut writer, &map, start_pos);
     part2(&mut writer, &map, start_pos);
+
+    if options.report || options.export.is_some() {
+        let heat = splitter_heat(&map, start_pos);
+        if options.report {
+            print_heatmaps(&map, &heat);
+        }
+        if let Some((mut export_writer, format)) = options.create_export() {
+            match format {
+                "csv" => export_heat_csv(&mut export_writer, &heat),
+                _ => panic!("day 7 can't export to {:?} - try csv", format),
+            }
+        }
+    }
 }
 
-fn part1<W: Write>(writer: &mut BufWriter<W>, map: &[Vec<Cell>], start_pos: (usize, usize)) {
-    let mut splits = 0;
+// Sends a beam with the given payload downwards from start_pos, one row at a time, until every
+// beam has either left the map (off the bottom or off either side) or been absorbed. on_hit is
+// called once for every non-empty cell that a beam enters, with that beam's (merged) payload.
+// Returns the payloads of all the beams that left the map.
+//
+// Each row's beams are kept in a dense array indexed by column, rather than a map, since almost
+// every column ends up with a beam in it anyway. The two rows' worth of arrays are reused rather
+// than reallocated for every row.
+fn simulate<P: Payload, F: FnMut(Cell, (usize, usize), &P)>(
+    map: &[Vec<Cell>],
+    start_pos: (usize, usize),
+    payload: P,
+    mut on_hit: F,
+) -> Vec<P> {
+    let mut exited = Vec::new();
+
+    let mut beams: Vec<Option<P>> = vec![None; map[start_pos.1].len()];
+    beams[start_pos.0] = Some(payload);
+    let mut new_beams: Vec<Option<P>> = Vec::new();
+    for (row, cells) in map.iter().enumerate().skip(start_pos.1 + 1) {
+        new_beams.clear();
+        new_beams.resize(cells.len(), None);
+        for (col, payload) in beams.iter().enumerate() {
+            let Some(payload) = payload else {
+                continue;
+            };
 
-    let mut beams: HashSet<(usize, usize)> = HashSet::new();
-    beams.insert(start_pos);
-    'outer: loop {
-        let mut new_beams: HashSet<(usize, usize)> = HashSet::new();
-        for mut beam in beams {
-            beam = Dir4::Down.apply_delta_to_usizes(beam);
-            if beam.1 >= map.len() {
-                break 'outer;
+            let Some(&cell) = cells.get(col) else {
+                // this row is shorter than the one above, so the beam has fallen off the side
+                exited.push(payload.clone());
+                continue;
+            };
+            if cell != Cell::Empty {
+                on_hit(cell, (col, row), payload);
             }
-            if map[beam.1][beam.0] == Cell::Splitter {
-                let b1 = Dir4::Left.apply_delta_to_usizes(beam);
-                let b2 = Dir4::Right.apply_delta_to_usizes(beam);
-                new_beams.insert(b1);
-                new_beams.insert(b2);
-                splits += 1;
-            } else {
-                new_beams.insert(beam);
+
+            for exit in cell.exits() {
+                let new_col = match exit {
+                    Dir4::Down => Some(col),
+                    Dir4::Left => col.checked_sub(1),
+                    Dir4::Right => Some(col + 1).filter(|&c| c < cells.len()),
+                    Dir4::Up => panic!("beams can't travel upwards"),
+                };
+                let Some(new_col) = new_col else {
+                    exited.push(payload.clone());
+                    continue;
+                };
+
+                match &mut new_beams[new_col] {
+                    Some(e) => e.merge(payload),
+                    e @ None => *e = Some(payload.clone()),
+                }
             }
         }
-        beams = new_beams;
+        mem::swap(&mut beams, &mut new_beams);
     }
 
+    exited.extend(beams.into_iter().flatten());
+    exited
+}
+
+fn part1<W: Write>(writer: &mut BufWriter<W>, map: &[Vec<Cell>], start_pos: (usize, usize)) {
+    let mut splits = 0;
+    simulate(map, start_pos, (), |cell, _, _| {
+        if cell == Cell::Splitter {
+            splits += 1;
+        }
+    });
+
     printwriteln!(writer, "{}", splits).unwrap();
 }
 
 fn part2<W: Write>(writer: &mut BufWriter<W>, map: &[Vec<Cell>], start_pos: (usize, usize)) {
-    let mut beams: HashMap<(usize, usize), i64> = HashMap::new();
-    beams.insert(start_pos, 1);
-    'outer: loop {
-        let mut new_beams: HashMap<(usize, usize), i64> = HashMap::new();
-        for (beam, timelines) in &beams {
-            let beam = Dir4::Down.apply_delta_to_usizes(*beam);
-            if beam.1 >= map.len() {
-                break 'outer;
+    let beams = simulate(map, start_pos, BigUint::from(1u32), |_, _, _| {});
+
+    let timelines: BigUint = beams.iter().sum();
+    printwriteln!(writer, "{}", timelines).unwrap();
+}
+
+// How much use each splitter got: how many beams hit it in part 1, and how many timelines passed
+// through it in part 2.
+type SplitterHeat = HashMap<(usize, usize), (u64, BigUint)>;
+
+fn splitter_heat(map: &[Vec<Cell>], start_pos: (usize, usize)) -> SplitterHeat {
+    let mut heat = SplitterHeat::new();
+    for (row, cells) in map.iter().enumerate() {
+        for (col, cell) in cells.iter().enumerate() {
+            if *cell == Cell::Splitter {
+                heat.insert((col, row), (0, BigUint::ZERO));
             }
-            if map[beam.1][beam.0] == Cell::Splitter {
-                let b1 = Dir4::Left.apply_delta_to_usizes(beam);
-                let b2 = Dir4::Right.apply_delta_to_usizes(beam);
-                new_beams
-                    .entry(b1)
-                    .and_modify(|e| *e += *timelines)
-                    .or_insert(*timelines);
-                new_beams
-                    .entry(b2)
-                    .and_modify(|e| *e += *timelines)
-                    .or_insert(*timelines);
-            } else {
-                new_beams
-                    .entry(beam)
-                    .and_modify(|e| *e += *timelines)
-                    .or_insert(*timelines);
+        }
+    }
+
+    simulate(map, start_pos, (), |cell, pos, _| {
+        if cell == Cell::Splitter {
+            heat.get_mut(&pos).unwrap().0 += 1;
+        }
+    });
+    simulate(
+        map,
+        start_pos,
+        BigUint::from(1u32),
+        |cell, pos, timelines| {
+            if cell == Cell::Splitter {
+                heat.get_mut(&pos).unwrap().1 += timelines;
             }
+        },
+    );
+
+    heat
+}
+
+// Draws the map twice, once per part, with each splitter replaced by a digit showing how heavily
+// it was used: 0 for not at all, up to 9 for the most-used splitter. Part 2 counts span many
+// orders of magnitude, so they're bucketed by their number of bits rather than linearly.
+fn print_heatmaps(map: &[Vec<Cell>], heat: &SplitterHeat) {
+    let max_hits = heat.values().map(|h| h.0).max().unwrap_or(0);
+    let max_bits = heat.values().map(|h| h.1.bits()).max().unwrap_or(0);
+
+    let level = |value: u64, max: u64| -> char {
+        if value == 0 {
+            '0'
+        } else if max <= 1 {
+            '9'
+        } else {
+            char::from_digit((1 + (value - 1) * 8 / (max - 1)) as u32, 10).unwrap()
         }
-        beams = new_beams;
+    };
+
+    println!("part 1: beams hitting each splitter (max {})", max_hits);
+    print_heatmap(map, |pos| level(heat[&pos].0, max_hits));
+    println!();
+    println!(
+        "part 2: timelines passing through each splitter (max {} bits)",
+        max_bits
+    );
+    print_heatmap(map, |pos| level(heat[&pos].1.bits(), max_bits));
+}
+
+fn print_heatmap<F: Fn((usize, usize)) -> char>(map: &[Vec<Cell>], splitter_char: F) {
+    for (row, cells) in map.iter().enumerate() {
+        let line: String = cells
+            .iter()
+            .enumerate()
+            .map(|(col, cell)| match cell {
+                Cell::Splitter => splitter_char((col, row)),
+                _ => char::from(*cell),
+            })
+            .collect();
+        println!("{}", line);
     }
+}
 
-    let timelines: i64 = beams.values().sum();
-    printwriteln!(writer, "{}", timelines).unwrap();
+fn export_heat_csv<W: Write>(writer: &mut BufWriter<W>, heat: &SplitterHeat) {
+    let mut splitters: Vec<_> = heat.iter().collect();
+    splitters.sort_by_key(|((col, row), _)| (*row, *col));
+
+    writeln!(writer, "row,col,part1_beams,part2_timelines").unwrap();
+    for ((col, row), (beams, timelines)) in splitters {
+        writeln!(writer, "{},{},{},{}", row, col, beams, timelines).unwrap();
+    }
 }
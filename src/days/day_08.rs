@@ -1,13 +1,17 @@
 use std::{
-    cmp::{max, min},
-    collections::HashSet,
+    collections::BinaryHeap,
     io::{BufWriter, Write},
+    mem,
 };
 
-use aoclib_rs::{pairwise_iter, prep_io, printwriteln};
+use aoclib_rs::{prep_io, printwriteln};
+
+use super::Options;
 
 // TODO: refactor into aoclib-rs
-#[derive(Clone, Debug, PartialEq, Eq, Hash, Copy)]
+//
+// Ordered lexicographically, by x, then y, then z.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Copy)]
 pub struct Point3d {
     x: i64,
     y: i64,
@@ -18,17 +22,64 @@ impl Point3d {
     fn new(x: i64, y: i64, z: i64) -> Self {
         Self { x, y, z }
     }
-    fn dist(&self, other: &Self) -> f64 {
-        ((self.x as f64 - other.x as f64).powi(2)
-            + (self.y as f64 - other.y as f64).powi(2)
-            + (self.z as f64 - other.z as f64).powi(2))
-        .sqrt()
+    // Squared, so it stays an exact integer - it sorts the same as the real distance anyway. Done
+    // in i128 so that it can't overflow for any i64 coordinates.
+    fn dist_squared(&self, other: &Self) -> i128 {
+        (self.x as i128 - other.x as i128).pow(2)
+            + (self.y as i128 - other.y as i128).pow(2)
+            + (self.z as i128 - other.z as i128).pow(2)
     }
 }
 
-type Dist = (Point3d, Point3d, f64);
+// A pair of points, and the squared distance between them. p1 is always the lesser of the two
+// points, and i1 and i2 are the indices of p1 and p2 in the list of points.
+//
+// Pairs are ordered by the order in which they get connected: closest first, with ties broken by
+// comparing p1s and then p2s (lexicographically - see Point3d). That way exactly the same circuits
+// get formed no matter what order the points are listed in. Only pairs of identical points are
+// left to fall back on their indices.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Copy)]
+struct Dist {
+    dist_squared: i128,
+    p1: Point3d,
+    p2: Point3d,
+    i1: usize,
+    i2: usize,
+}
+
+impl Dist {
+    fn new(points: &[Point3d], i: usize, j: usize) -> Self {
+        let (i1, i2) = if (points[i], i) <= (points[j], j) {
+            (i, j)
+        } else {
+            (j, i)
+        };
+
+        Self {
+            dist_squared: points[i1].dist_squared(&points[i2]),
+            p1: points[i1],
+            p2: points[i2],
+            i1,
+            i2,
+        }
+    }
+}
+
+// Whether the pair of points i1 and i2, dist_squared apart, gets connected before the pair j1 and
+// j2. Distances almost never tie, so the full Dists are only built when they do.
+fn connects_before(
+    points: &[Point3d],
+    (dist_squared, i1, i2): (i128, usize, usize),
+    (other_dist_squared, j1, j2): (i128, usize, usize),
+) -> bool {
+    if dist_squared != other_dist_squared {
+        dist_squared < other_dist_squared
+    } else {
+        Dist::new(points, i1, i2) < Dist::new(points, j1, j2)
+    }
+}
 
-pub fn run() {
+pub fn run(options: &Options) {
     let mut contents = String::new();
     let (mut writer, contents) = prep_io(&mut contents, 8).unwrap();
     let points: Vec<_> = contents
@@ -43,78 +94,254 @@ pub fn run() {
         })
         .collect();
 
-    let mut dists: Vec<Dist> = Vec::new();
+    part1(
+        &mut writer,
+        &points,
+        options.pairs.unwrap_or(1000),
+        options.top.unwrap_or(3),
+        options.report,
+    );
+
+    let mst = minimum_spanning_tree(&points);
+    part2(&mut writer, &points, &mst);
+
+    if let Some((mut export_writer, format)) = options.create_export() {
+        match format {
+            "csv" => export_mst_csv(&mut export_writer, &mst, points.len()),
+            "dot" => export_mst_dot(&mut export_writer, &mst, &points),
+            _ => panic!("day 8 can't export to {:?} - try csv or dot", format),
+        }
+    }
+}
+
+// The k closest pairs of points, in connection order. Rather than materialising and sorting all
+// n² pairs, this keeps a bounded max-heap of the best k pairs seen so far.
+fn closest_pairs(points: &[Point3d], k: usize) -> Vec<Dist> {
+    if k == 0 {
+        return Vec::new();
+    }
+
+    let mut heap: BinaryHeap<Dist> = BinaryHeap::with_capacity(k);
+    for i in 0..points.len() {
+        for j in (i + 1)..points.len() {
+            if heap.len() < k {
+                heap.push(Dist::new(points, i, j));
+                continue;
+            }
 
-    // TODO: slightly inefficient - makes copies of points instead of passing by ref
-    for (p1, p2) in pairwise_iter(&points) {
-        dists.push((p1, p2, p1.dist(&p2)));
+            // once the heap is full, nearly every pair is further away than all of it, which the
+            // distance alone is enough to rule out
+            let dist_squared = points[i].dist_squared(&points[j]);
+            if let Some(mut furthest) = heap.peek_mut()
+                && dist_squared <= furthest.dist_squared
+            {
+                let candidate = Dist::new(points, i, j);
+                if candidate < *furthest {
+                    *furthest = candidate;
+                }
+            }
+        }
     }
-    dists.sort_by(|a, b| a.2.partial_cmp(&b.2).unwrap());
 
-    part1(&mut writer, &dists);
-    part2(&mut writer, &dists);
+    heap.into_sorted_vec()
 }
 
-type Circuit = HashSet<Point3d>;
+// The minimum spanning tree over all the points, in connection order (ie. the order Kruskal's
+// algorithm would add the edges in). Since every pair of points is a candidate edge, this uses the
+// dense O(n²) form of Prim's algorithm, which only needs O(n) memory rather than all n² pairs.
+fn minimum_spanning_tree(points: &[Point3d]) -> Vec<Dist> {
+    let mut edges = Vec::with_capacity(points.len().saturating_sub(1));
+    if points.is_empty() {
+        return edges;
+    }
 
-fn part1<W: Write>(writer: &mut BufWriter<W>, dists: &[Dist]) {
-    let (circuits, _, _) = solve_for_n_pairs(dists, 1000);
-    printwriteln!(
-        writer,
-        "{}",
-        circuits[circuits.len() - 1].len()
-            * circuits[circuits.len() - 2].len()
-            * circuits[circuits.len() - 3].len()
-    )
-    .unwrap();
+    // the points not yet in the tree, and in the same positions, the shortest edge connecting each
+    // of them to the tree so far: its squared length and the point in the tree at the other end.
+    // The tree starts off as just point 0.
+    let mut outside: Vec<usize> = (1..points.len()).collect();
+    let mut best_edge: Vec<(i128, usize)> = outside
+        .iter()
+        .map(|&p| (points[0].dist_squared(&points[p]), 0))
+        .collect();
+
+    while !outside.is_empty() {
+        let edge = |i: usize| (best_edge[i].0, best_edge[i].1, outside[i]);
+        let closest = (1..outside.len()).fold(0, |closest, i| {
+            if connects_before(points, edge(i), edge(closest)) {
+                i
+            } else {
+                closest
+            }
+        });
+
+        let added = outside.swap_remove(closest);
+        let (_, from) = best_edge.swap_remove(closest);
+        edges.push(Dist::new(points, from, added));
+
+        for (&p, best) in outside.iter().zip(best_edge.iter_mut()) {
+            let dist_squared = points[added].dist_squared(&points[p]);
+            if connects_before(points, (dist_squared, added, p), (best.0, best.1, p)) {
+                *best = (dist_squared, added);
+            }
+        }
+    }
+
+    edges.sort();
+    edges
 }
 
-type Solution = (Vec<Circuit>, Point3d, Point3d);
+// A disjoint-set forest over point indices, where each set is one circuit. Uses union by size and
+// path compression, so merging and looking up circuits are both effectively constant time.
+struct Circuits {
+    parent: Vec<usize>,
+    size: Vec<usize>,
+    count: usize,
+}
 
-fn solve_for_n_pairs(dists: &[Dist], n: usize) -> Solution {
-    let mut pushed: HashSet<Point3d> = HashSet::new();
-    let mut circuits: Vec<Circuit> = Vec::new();
-    for d in dists.iter().take(n) {
-        if !pushed.contains(&d.0) {
-            circuits.push(HashSet::from([d.0]));
-            pushed.insert(d.0);
+impl Circuits {
+    // Every point starts off in its own single-point circuit.
+    fn new(num_points: usize) -> Self {
+        Self {
+            parent: (0..num_points).collect(),
+            size: vec![1; num_points],
+            count: num_points,
         }
-        if !pushed.contains(&d.1) {
-            circuits.push(HashSet::from([d.1]));
-            pushed.insert(d.1);
+    }
+
+    fn find(&mut self, p: usize) -> usize {
+        let mut root = p;
+        while self.parent[root] != root {
+            root = self.parent[root];
         }
+
+        let mut p = p;
+        while self.parent[p] != root {
+            let next = self.parent[p];
+            self.parent[p] = root;
+            p = next;
+        }
+
+        root
     }
 
-    let mut i = 0;
-    loop {
-        let i1 = find_circuit_containing(&circuits, &dists[i].0);
-        let i2 = find_circuit_containing(&circuits, &dists[i].1);
-        merge_circuits(&mut circuits, i1, i2);
-        if i == n - 2 || circuits.len() == 1 {
-            break;
+    // Returns false if p1 and p2 were already in the same circuit.
+    fn merge(&mut self, p1: usize, p2: usize) -> bool {
+        let (mut r1, mut r2) = (self.find(p1), self.find(p2));
+        if r1 == r2 {
+            return false;
         }
-        i += 1;
+
+        if self.size[r1] < self.size[r2] {
+            mem::swap(&mut r1, &mut r2);
+        }
+        self.parent[r2] = r1;
+        self.size[r1] += self.size[r2];
+        self.count -= 1;
+        true
     }
 
-    circuits.sort_by_key(|e| e.len());
+    fn count(&self) -> usize {
+        self.count
+    }
 
-    (circuits, dists[i].0, dists[i].1)
+    // The size of every circuit, smallest first.
+    fn sizes(&self) -> Vec<usize> {
+        let mut sizes: Vec<_> = (0..self.parent.len())
+            .filter(|&p| self.parent[p] == p)
+            .map(|p| self.size[p])
+            .collect();
+        sizes.sort();
+        sizes
+    }
 }
 
-fn find_circuit_containing(circuits: &[Circuit], p: &Point3d) -> usize {
-    circuits.iter().position(|c| c.contains(p)).unwrap()
+fn part1<W: Write>(
+    writer: &mut BufWriter<W>,
+    points: &[Point3d],
+    pairs: usize,
+    top: usize,
+    report: bool,
+) {
+    let dists = closest_pairs(points, pairs);
+    let (circuits, _) = solve_for_n_pairs(points.len(), &dists, dists.len());
+    if circuits.len() < top {
+        panic!(
+            "can't multiply the {} largest circuits, there are only {}",
+            top,
+            circuits.len()
+        );
+    }
+
+    let product: usize = circuits.iter().rev().take(top).product();
+    printwriteln!(writer, "{}", product).unwrap();
+
+    if report {
+        print_circuit_report(&circuits, dists.len(), top);
+    }
 }
 
-fn merge_circuits(circuits: &mut Vec<Circuit>, i1: usize, i2: usize) {
-    if i1 == i2 {
-        return;
+// Prints how many circuits there are of each size, biggest first, eg:
+//
+// 10 pairs connected, 11 circuits
+// size  circuits
+//    5         1
+//    4         1
+//    2         2
+//    1         7
+// top 3: 5 * 4 * 2 = 40
+fn print_circuit_report(circuits: &[usize], pairs: usize, top: usize) {
+    println!("{} pairs connected, {} circuits", pairs, circuits.len());
+
+    let mut distribution: Vec<(usize, usize)> = Vec::new();
+    for &size in circuits.iter().rev() {
+        match distribution.last_mut() {
+            Some((s, count)) if *s == size => *count += 1,
+            _ => distribution.push((size, 1)),
+        }
+    }
+
+    println!("size  circuits");
+    for (size, count) in distribution {
+        println!("{:>4}  {:>8}", size, count);
     }
-    let c2 = circuits.remove(max(i1, i2));
-    circuits[min(i1, i2)].extend(c2);
+
+    let top_sizes: Vec<_> = circuits.iter().rev().take(top).collect();
+    println!(
+        "top {}: {} = {}",
+        top,
+        top_sizes
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>()
+            .join(" * "),
+        top_sizes.into_iter().product::<usize>()
+    );
 }
 
-fn part2<W: Write>(writer: &mut BufWriter<W>, dists: &[Dist]) {
-    let (circuits, p1, p2) = solve_for_n_pairs(dists, dists.len());
+// Circuit sizes (smallest first), and the last pair of points to be connected that merged two
+// circuits together.
+type Solution = (Vec<usize>, Option<(usize, usize)>);
+
+// Connects the n closest pairs of points, stopping early if everything ends up in one circuit.
+fn solve_for_n_pairs(num_points: usize, dists: &[Dist], n: usize) -> Solution {
+    let mut circuits = Circuits::new(num_points);
+    let mut last_merged = None;
+    for d in dists.iter().take(n) {
+        if circuits.merge(d.i1, d.i2) {
+            last_merged = Some((d.i1, d.i2));
+            if circuits.count() == 1 {
+                break;
+            }
+        }
+    }
+
+    (circuits.sizes(), last_merged)
+}
+
+// Kruskal's algorithm only ever merges circuits using edges from the minimum spanning tree, so
+// that's all that needs to be fed in here.
+fn part2<W: Write>(writer: &mut BufWriter<W>, points: &[Point3d], mst: &[Dist]) {
+    let (circuits, last_merged) = solve_for_n_pairs(points.len(), mst, mst.len());
     if circuits.len() != 1 {
         panic!(
             "something went wrong, still have {} circuits",
@@ -122,5 +349,61 @@ fn part2<W: Write>(writer: &mut BufWriter<W>, dists: &[Dist]) {
         );
     }
 
-    printwriteln!(writer, "{}", p1.x * p2.x).unwrap();
+    let (p1, p2) = last_merged.unwrap();
+    printwriteln!(writer, "{}", points[p1].x * points[p2].x).unwrap();
+}
+
+// One row per minimum spanning tree edge, in the order the edges get connected, along with how
+// many circuits are left once each one has been connected.
+fn export_mst_csv<W: Write>(writer: &mut BufWriter<W>, mst: &[Dist], num_points: usize) {
+    writeln!(
+        writer,
+        "step,x1,y1,z1,x2,y2,z2,distance_squared,distance,circuits"
+    )
+    .unwrap();
+    for (i, d) in mst.iter().enumerate() {
+        writeln!(
+            writer,
+            "{},{},{},{},{},{},{},{},{},{}",
+            i + 1,
+            d.p1.x,
+            d.p1.y,
+            d.p1.z,
+            d.p2.x,
+            d.p2.y,
+            d.p2.z,
+            d.dist_squared,
+            (d.dist_squared as f64).sqrt(),
+            num_points - (i + 1)
+        )
+        .unwrap();
+    }
+}
+
+// An undirected Graphviz graph of the minimum spanning tree. Each edge is labelled with the step
+// it was connected in and its length, and the final edge (the answer to part 2) is drawn in red.
+fn export_mst_dot<W: Write>(writer: &mut BufWriter<W>, mst: &[Dist], points: &[Point3d]) {
+    writeln!(writer, "graph mst {{").unwrap();
+    writeln!(writer, "    node [shape=box];").unwrap();
+    for (i, p) in points.iter().enumerate() {
+        writeln!(writer, "    p{} [label=\"{},{},{}\"];", i, p.x, p.y, p.z).unwrap();
+    }
+    for (i, d) in mst.iter().enumerate() {
+        let colour = if i == mst.len() - 1 {
+            ", color=red"
+        } else {
+            ""
+        };
+        writeln!(
+            writer,
+            "    p{} -- p{} [label=\"#{}: {:.1}\"{}];",
+            d.i1,
+            d.i2,
+            i + 1,
+            (d.dist_squared as f64).sqrt(),
+            colour
+        )
+        .unwrap();
+    }
+    writeln!(writer, "}}").unwrap();
 }
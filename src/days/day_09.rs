@@ -1,6 +1,9 @@
-use std::io::{BufWriter, Write};
+use std::{
+    cmp::max,
+    io::{BufWriter, Write},
+};
 
-use aoclib_rs::{pairwise_iter, prep_io, printwriteln};
+use aoclib_rs::{prep_io, printwriteln};
 
 pub fn run() {
     let mut contents = String::new();
@@ -21,10 +24,119 @@ pub fn run() {
 
 type Point = (i64, i64);
 
+fn area(p1: &Point, p2: &Point) -> i64 {
+    ((p1.0 - p2.0).abs() + 1) * ((p1.1 - p2.1).abs() + 1)
+}
+
 fn part1<W: Write>(writer: &mut BufWriter<W>, points: &[Point]) {
-    let area = |(p1, p2): &(Point, Point)| ((p1.0 - p2.0).abs() + 1) * ((p1.1 - p2.1).abs() + 1);
+    printwriteln!(writer, "{}", max_area(points)).unwrap();
+}
+
+// Any pair of opposite corners either goes from the min-x/min-y corner to the max-x/max-y corner,
+// or from the min-x/max-y corner to the max-x/min-y corner. The second kind is just the first kind
+// with the y axis flipped, so we find the best of each separately. Runs in O(n log n).
+fn max_area(points: &[Point]) -> i64 {
+    if points.is_empty() {
+        return 0;
+    }
+
+    let flipped: Vec<Point> = points.iter().map(|&(x, y)| (x, -y)).collect();
+    max(max_area_rising(points), max_area_rising(&flipped))
+}
+
+// The largest rectangle with a min-x/min-y corner at one point and a max-x/max-y corner at
+// another.
+//
+// If some other point is both left of and below the min corner, using that point instead can only
+// make the rectangle bigger, so the min corner only needs to be picked from the "lower staircase":
+// the points which no other point is both left of and below. Likewise, the max corner only needs
+// to be picked from the "upper staircase". Both staircases run left-to-right and downwards.
+//
+// Then, for lower staircase points l1 left of l2, and upper staircase points u1 left of u2, it
+// turns out that
+//
+//     area(l1, u1) + area(l2, u2) >= area(l1, u2) + area(l2, u1)
+//
+// (expand the products - the difference is (u2.x - u1.x) * (l1.y - l2.y) + (l2.x - l1.x) *
+// (u1.y - u2.y), and none of those are negative). That means the best partner for l2 is never to
+// the left of the best partner for l1, so we can divide and conquer over the lower staircase,
+// narrowing down the window of upper staircase candidates as we go.
+fn max_area_rising(points: &[Point]) -> i64 {
+    let lower = lower_staircase(points);
+    let upper = upper_staircase(points);
+    best_partners(&lower, &upper, 0, lower.len(), 0, upper.len() - 1)
+}
+
+// Points with no other point both left of (or level with) and below (or level with) them, sorted
+// by x ascending (and so y descending).
+fn lower_staircase(points: &[Point]) -> Vec<Point> {
+    let mut sorted = points.to_vec();
+    sorted.sort();
+
+    let mut staircase: Vec<Point> = Vec::new();
+    for p in sorted {
+        if staircase.last().is_none_or(|last| p.1 < last.1) {
+            staircase.push(p);
+        }
+    }
+    staircase
+}
+
+// Points with no other point both right of (or level with) and above (or level with) them, sorted
+// by x ascending (and so y descending).
+fn upper_staircase(points: &[Point]) -> Vec<Point> {
+    let mut sorted = points.to_vec();
+    sorted.sort_by(|a, b| b.cmp(a));
+
+    let mut staircase: Vec<Point> = Vec::new();
+    for p in sorted {
+        if staircase.last().is_none_or(|last| p.1 > last.1) {
+            staircase.push(p);
+        }
+    }
+    staircase.reverse();
+    staircase
+}
+
+// The best area for lower[start..end], given that their best partners are all within
+// upper[opt_start..=opt_end]. A pair only counts if the upper point really is above and to the
+// right of the lower point - otherwise it's a rectangle of the other kind, which is handled
+// separately. Every lower staircase point has at least one upper staircase point that counts
+// (possibly itself).
+fn best_partners(
+    lower: &[Point],
+    upper: &[Point],
+    start: usize,
+    end: usize,
+    opt_start: usize,
+    opt_end: usize,
+) -> i64 {
+    if start >= end {
+        return 0;
+    }
+
+    let mid = start + (end - start) / 2;
+    let l = lower[mid];
+    let mut best = 0;
+    let mut best_j = opt_start;
+    for (j, u) in upper.iter().enumerate().take(opt_end + 1).skip(opt_start) {
+        if u.0 < l.0 || u.1 < l.1 {
+            continue;
+        }
+
+        // ties go to the rightmost candidate, which keeps the best partners in order
+        let a = area(&l, u);
+        if a >= best {
+            best = a;
+            best_j = j;
+        }
+    }
 
-    // TODO: slightly inefficient - makes copies of points instead of passing by ref
-    let max_area_points = pairwise_iter(points).max_by_key(area).unwrap();
-    printwriteln!(writer, "{}", area(&max_area_points)).unwrap();
+    max(
+        best,
+        max(
+            best_partners(lower, upper, start, mid, opt_start, best_j),
+            best_partners(lower, upper, mid + 1, end, best_j, opt_end),
+        ),
+    )
 }
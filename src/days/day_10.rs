@@ -1,732 +1,515 @@
 use std::{
-    cmp::Ordering,
-    fmt,
-    fmt::Formatter,
     io::{BufWriter, Write},
-    ops::{Deref, DerefMut, Index, IndexMut, Mul, MulAssign},
+    str::FromStr,
+    time::{Duration, Instant},
 };
 
-use aoclib_rs::{option_min_max::OptionMinMax, prep_io, printwriteln};
-
-use num_rational::Rational64 as R64;
+use aoclib_rs::{prep_io, printwriteln};
+use num_integer::Integer;
+use num_traits::{One, Zero};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use tracing::{debug, trace};
+
+use super::Options;
+use crate::{
+    ilp,
+    linalg::{Matrix, RowVec, Scalar, scalar},
+};
 
 #[derive(Debug, Clone)]
 struct Machine {
-    lights_actual: Vec<Light>,
-    lights_goal: Vec<Light>,
+    num_lights: usize,
+    lights_goal: Lights,
     buttons: Vec<Button>,
     joltage_reqs: Vec<Joltage>,
 }
 
 impl Machine {
-    fn new(lights_goal: Vec<Light>, buttons: Vec<Button>, joltage_reqs: Vec<Joltage>) -> Self {
+    fn new(
+        num_lights: usize,
+        lights_goal: Lights,
+        buttons: Vec<Button>,
+        joltage_reqs: Vec<Joltage>,
+    ) -> Self {
         Self {
-            lights_actual: Self::default_lights(lights_goal.len()),
+            num_lights,
             lights_goal,
             buttons,
             joltage_reqs,
         }
     }
 
-    fn default_lights(len: usize) -> Vec<Light> {
-        vec![Light::default(); len]
-    }
-
-    fn goal_achieved(&self) -> bool {
-        self.lights_actual == self.lights_goal
-    }
-
-    fn reset(&mut self) {
-        self.lights_actual = Self::default_lights(self.lights_actual.len());
-    }
+    // The fewest buttons to press to get the lights into their goal state, as a bitmask of the
+    // buttons. Pressing a button twice is the same as not pressing it at all, so this is a system
+    // of linear equations over GF(2): one per light, saying that the XOR of the presses of the
+    // buttons wired to it has to match whether it should end up on. Returns None if there's no
+    // way to do it.
+    fn min_light_presses(&self) -> Option<Presses> {
+        // each equation is the set of buttons wired to the light, and whether it should be on
+        let mut rows: Vec<(Presses, bool)> = (0..self.num_lights)
+            .map(|light| {
+                let buttons = self
+                    .buttons
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, b)| b.contains(&light))
+                    .fold(0, |mask, (i, _)| mask | (1 << i));
+                (buttons, self.lights_goal & (1 << light) != 0)
+            })
+            .collect();
 
-    fn min_button_presses(&mut self) -> i64 {
-        let mut presses: Presses = vec![false; self.buttons.len()];
-        let mut min = OptionMinMax(None);
-        loop {
-            let num_presses = self.press_buttons(&presses);
-            if self.goal_achieved() {
-                min = min.min(num_presses);
+        // Gauss-Jordan elimination, where adding one row to another is just XOR
+        let mut pivot_cols = Vec::new();
+        for col in 0..self.buttons.len() {
+            let rank = pivot_cols.len();
+            let Some(pivot) = (rank..rows.len()).find(|&r| rows[r].0 & (1 << col) != 0) else {
+                continue;
+            };
+            rows.swap(rank, pivot);
+
+            let (pivot_buttons, pivot_on) = rows[rank];
+            for (r, row) in rows.iter_mut().enumerate() {
+                if r != rank && row.0 & (1 << col) != 0 {
+                    row.0 ^= pivot_buttons;
+                    row.1 ^= pivot_on;
+                }
             }
-            self.reset();
+            pivot_cols.push(col);
+        }
 
-            if increment_presses(&mut presses) {
-                break;
-            }
+        // anything left over has no buttons, so it can't turn a light on
+        if rows[pivot_cols.len()..].iter().any(|&(_, on)| on) {
+            return None;
         }
-        min.0.unwrap()
-    }
 
-    fn press_buttons(&mut self, presses: &Presses) -> i64 {
-        let mut num_presses = 0;
-        for (i, press) in presses.iter().enumerate() {
-            if *press {
-                self.press_button(i);
-                num_presses += 1;
+        // one solution is to leave all the free buttons alone, which decides all the others
+        let mut particular: Presses = 0;
+        for (row, &col) in pivot_cols.iter().enumerate() {
+            if rows[row].1 {
+                particular |= 1 << col;
             }
         }
-        num_presses
-    }
 
-    fn press_button(&mut self, i: usize) {
-        for b in &self.buttons[i] {
-            self.lights_actual[*b].toggle();
-        }
-    }
-}
-
-impl From<&str> for Machine {
-    // "[.#.#] (0) (1,2) {2,4,6,8}"
-    fn from(line: &str) -> Self {
-        // ["[.#.#", "(0) (1,2) {2,4,6,8}"]
-        let mut line_split = line.split("] ");
-
-        // ".#.#"
-        let lights_str = &line_split.next().unwrap()[1..];
-        let lights: Vec<Light> = lights_str.chars().map(Light::from).collect();
-
-        // ["(0) (1,2) ", "2,4,6,8}"]
-        let mut line_split_2 = line_split.next().unwrap().split("{");
-
-        // "(0) (1,2) "
-        let buttons_str = line_split_2.next().unwrap();
-
-        // ["(0", "(1,2", ""]
-        let buttons_split = buttons_str.split(") ");
-        let buttons: Vec<Button> = buttons_split
-            .filter(|b| !b.is_empty())
-            .map(|b| {
-                // ["0"]
-                // ["1", "2"]
-                let b_split = b[1..].split(",");
-                b_split.map(|i| i.parse().unwrap()).collect()
+        // every solution is that one XORed with some combination of the null space basis: one
+        // vector per free button, which presses it along with whichever pivot buttons undo it
+        let null_space: Vec<Presses> = (0..self.buttons.len())
+            .filter(|col| !pivot_cols.contains(col))
+            .map(|free| {
+                let mut v: Presses = 1 << free;
+                for (row, &col) in pivot_cols.iter().enumerate() {
+                    if rows[row].0 & (1 << free) != 0 {
+                        v |= 1 << col;
+                    }
+                }
+                v
             })
             .collect();
 
-        // "2,4,6,8}"
-        let joltages_str = line_split_2.next().unwrap();
-
-        // ["2", "4", "6", "8"]
-        let joltages_split = joltages_str[..(joltages_str.len() - 1)].split(",");
-        let joltages: Vec<Joltage> = joltages_split.map(|j| j.parse().unwrap()).collect();
-
-        Machine::new(lights, buttons, joltages)
-    }
-}
-
-#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
-enum Light {
-    On,
-    Off,
-}
-
-impl Light {
-    fn default() -> Self {
-        Light::Off
-    }
-
-    fn toggle(&mut self) {
-        *self = match self {
-            Light::On => Light::Off,
-            Light::Off => Light::On,
-        };
-    }
-}
-
-impl From<char> for Light {
-    fn from(c: char) -> Self {
-        match c {
-            '#' => Light::On,
-            '.' => Light::Off,
-            _ => panic!("invalid input"),
+        // visit every combination in Gray code order, so each step only flips one basis vector
+        let mut presses = particular;
+        let mut min = particular;
+        for i in 1..(1u128 << null_space.len()) {
+            presses ^= null_space[i.trailing_zeros() as usize];
+            if presses.count_ones() < min.count_ones() {
+                min = presses;
+            }
         }
+        Some(min)
     }
-}
-
-type Button = Vec<usize>;
-type Joltage = i64;
-type Presses = Vec<bool>;
-
-pub fn run() {
-    let mut contents = String::new();
-    let (mut writer, contents) = prep_io(&mut contents, 10).unwrap();
-    let machines: Vec<Machine> = contents.iter().map(|line| Machine::from(*line)).collect();
-
-    part1(&mut writer, machines.clone());
-    part2(&mut writer, machines);
-}
 
-fn part1<W: Write>(writer: &mut BufWriter<W>, machines: Vec<Machine>) {
-    let mut total = 0;
-    for mut m in machines {
-        total += m.min_button_presses();
+    // eg. "[.##.]"
+    fn lights_goal_str(&self) -> String {
+        let lights: String = (0..self.num_lights)
+            .map(|i| {
+                if self.lights_goal & (1 << i) != 0 {
+                    '#'
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        format!("[{}]", lights)
     }
 
-    printwriteln!(writer, "{}", total).unwrap();
-}
-
-// TODO make an iterator for this in aoclib-rs
-fn increment_presses(presses: &mut Presses) -> bool {
-    let mut last = presses.len() - 1;
-    loop {
-        presses[last] = !presses[last];
-
-        if presses[last] {
-            break;
+    // A matrix representing the relationship between joltages and button presses.
+    fn joltage_matrix(&self) -> Matrix {
+        // For an example scenario, an input of "(1, 3) (4) (2, 4, 5) (0) (3) {4,3,1,8,3,1}" will
+        // give a matrix that initially looks like:
+        //
+        // [ [ 0 0 0 1 0 1 0 ]
+        //   [ 1 0 0 0 0 1 0 ]
+        //   [ 0 0 1 0 0 0 0 ]
+        //   [ 1 0 0 0 1 0 0 ]
+        //   [ 0 1 1 0 0 0 0 ]
+        //   [ 0 0 1 0 0 0 0 ]
+        //
+        // Where each column except the rightmost encodes the effects of one button.
+        let mut mat = Matrix::zeros(self.joltage_reqs.len(), self.buttons.len() + 1);
+        for (i, b) in self.buttons.iter().enumerate() {
+            for c in b {
+                mat[(*c, i)] = Scalar::one();
+            }
         }
 
-        match last.checked_sub(1) {
-            None => return true,
-            Some(l) => last = l,
+        // finish the matrix from above by putting the joltage values in the last column:
+        //
+        // [ [ 0 0 0 1 0 1 6 ]
+        //   [ 1 0 0 0 0 1 5 ]
+        //   [ 0 0 1 0 0 0 1 ]
+        //   [ 1 0 0 0 1 0 8 ]
+        //   [ 0 1 1 0 0 0 3 ]
+        //   [ 0 0 1 0 0 0 1 ] ]
+        //
+        // This matrix now represents a series of linear equations:
+        //
+        // * row 0:  b3 + b5 = 6
+        // * row 1:  b0 + b5 = 5
+        // * row 2:     b2   = 1
+        // * row 3:  b0 + b4 = 8
+        // * row 4:  b1 + b2 = 3
+        // * row 5:     b2   = 1
+        //
+        // where each bi is the number of presses of button i.
+        for (i, j) in self.joltage_reqs.iter().enumerate() {
+            mat[(i, self.buttons.len())] = scalar(*j);
         }
-    }
-    false
-}
 
-#[derive(Clone, Debug, PartialEq)]
-struct RowVec(Vec<R64>);
-
-impl RowVec {
-    fn zeros(len: usize) -> Self {
-        Self(vec![R64::ZERO; len])
+        mat
     }
 
-    fn add_assign(&mut self, rhs: &Self) -> anyhow::Result<()> {
-        if self.0.len() != rhs.0.len() {
-            anyhow::bail!(
-                "addition of RowVecs of different sizes: {} vs {}",
-                self.0.len(),
-                rhs.0.len()
-            );
+    // Like joltage_matrix, but with one row per light rather than one per joltage, and a 1 in the
+    // last column for each light that should end up on.
+    fn lights_matrix(&self) -> Matrix {
+        let mut mat = Matrix::zeros(self.num_lights, self.buttons.len() + 1);
+        for (i, b) in self.buttons.iter().enumerate() {
+            for c in b {
+                mat[(*c, i)] = Scalar::one();
+            }
         }
-
-        self.0
-            .iter_mut()
-            .enumerate()
-            .for_each(|(i, e)| *e += rhs.0[i]);
-
-        Ok(())
-    }
-
-    fn normalize(&mut self) {
-        let Some(leader_col) = self.leader_col() else {
-            return;
-        };
-
-        if self.0[leader_col] != R64::ONE {
-            let factor = self.0[leader_col].recip();
-            *self *= factor;
+        for light in 0..self.num_lights {
+            if self.lights_goal & (1 << light) != 0 {
+                mat[(light, self.buttons.len())] = Scalar::one();
+            }
         }
-    }
-
-    fn is_zeros(&self) -> bool {
-        fold_while(self.0.iter(), true, |_, v| {
-            let r = *v == R64::ZERO;
-            (r, r)
-        })
-    }
-
-    fn leader_col(&self) -> Option<usize> {
-        self.0.iter().position(|&e| e != R64::ZERO)
+        mat
     }
 }
 
-impl Deref for RowVec {
-    type Target = Vec<R64>;
-
-    fn deref(&self) -> &Self::Target {
-        &self.0
-    }
-}
+impl FromStr for Machine {
+    type Err = anyhow::Error;
 
-impl DerefMut for RowVec {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
-    }
-}
+    // "[.#.#] (0) (1,2) {2,4,6,8}"
+    fn from_str(line: &str) -> anyhow::Result<Self> {
+        let mut parser = MachineParser { line, pos: 0 };
 
-impl MulAssign<R64> for RowVec {
-    fn mul_assign(&mut self, rhs: R64) {
-        for cell in &mut self.0 {
-            *cell *= rhs;
+        // "[.#.#]"
+        parser.expect('[')?;
+        let lights_start = parser.pos;
+        let mut lights = Vec::new();
+        loop {
+            match parser.peek() {
+                Some(c @ ('#' | '.')) => lights.push(c == '#'),
+                Some(']') => break,
+                _ => return Err(parser.unexpected("'#', '.' or ']'")),
+            }
+            parser.pos += 1;
         }
-    }
-}
-
-impl Mul<R64> for RowVec {
-    type Output = RowVec;
-
-    fn mul(mut self, rhs: R64) -> Self::Output {
-        self *= rhs;
-        self
-    }
-}
-
-impl fmt::Display for RowVec {
-    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        write!(f, "[ ")?;
-        for v in &self.0 {
-            write!(f, "{} ", *v)?;
+        if lights.len() > Lights::BITS as usize {
+            return Err(parser.error_at(
+                lights_start,
+                format!("too many lights (at most {})", Lights::BITS),
+            ));
         }
-        write!(f, "]")?;
-        Ok(())
-    }
-}
-
-#[derive(Clone, Debug, PartialEq)]
-struct Matrix(Vec<RowVec>);
-
-impl Matrix {
-    fn new(m: Vec<Vec<R64>>) -> Self {
-        Self(m.into_iter().map(RowVec).collect())
-    }
-
-    fn zeros(rows: usize, cols: usize) -> Self {
-        if rows == 0 || cols == 0 {
-            Self(Vec::new())
-        } else {
-            Self(vec![RowVec::zeros(cols); rows])
+        let lights_goal = lights
+            .iter()
+            .enumerate()
+            .filter(|(_, on)| **on)
+            .fold(0, |mask, (i, _)| mask | (1 << i));
+        parser.expect(']')?;
+
+        // "(0) (1,2)"
+        let mut buttons = Vec::new();
+        let mut indices = Vec::new();
+        while parser.peek_past_whitespace() == Some('(') {
+            if buttons.len() == Presses::BITS as usize {
+                return Err(parser.error(format!("too many buttons (at most {})", Presses::BITS)));
+            }
+            let button = parser.list('(', ')')?;
+            buttons.push(button.iter().map(|&(_, i)| i as usize).collect());
+            indices.extend(button);
         }
-    }
 
-    fn rref(&mut self) {
-        self.r#ref();
-        for row in 0..self.0.len() {
-            self.eliminate_above_leader(row);
+        // "{2,4,6,8}"
+        if parser.peek_past_whitespace() != Some('{') {
+            return Err(parser.unexpected("'(' or '{'"));
         }
-        self.leader_sort();
-        self.normalize();
-    }
+        let joltages: Vec<Joltage> = parser.list('{', '}')?.into_iter().map(|(_, j)| j).collect();
 
-    fn r#ref(&mut self) {
-        self.leader_sort();
-        for row in 0..self.0.len() {
-            self.eliminate_below_leader(row);
+        parser.skip_whitespace();
+        if parser.peek().is_some() {
+            return Err(parser.unexpected("the end of the line"));
         }
-        self.leader_sort();
-    }
 
-    fn leader_sort(&mut self) {
-        self.0.sort_by(|a, b| {
-            for (i, ai) in a.iter().enumerate() {
-                let bi = b[i];
-                if *ai == R64::ZERO && bi != R64::ZERO {
-                    return Ordering::Greater;
-                } else if bi == R64::ZERO && *ai != R64::ZERO {
-                    return Ordering::Less;
-                }
+        for (col, i) in indices {
+            let i = i as usize;
+            if i >= lights.len() {
+                return Err(parser.error_at(
+                    col,
+                    format!(
+                        "button wired to light {}, but there are only {}",
+                        i,
+                        lights.len()
+                    ),
+                ));
+            }
+            if i >= joltages.len() {
+                return Err(parser.error_at(
+                    col,
+                    format!(
+                        "button wired to joltage {}, but there are only {}",
+                        i,
+                        joltages.len()
+                    ),
+                ));
             }
-            Ordering::Equal
-        });
-    }
-
-    fn eliminate_below_leader(&mut self, row: usize) {
-        let Some(leader_col) = self.0[row].leader_col() else {
-            return;
-        };
-
-        for i in (row + 1)..self.0.len() {
-            self.eliminate(row, i, leader_col);
         }
+
+        Ok(Machine::new(lights.len(), lights_goal, buttons, joltages))
     }
+}
 
-    fn eliminate(&mut self, selected_row: usize, other_row: usize, leader_col: usize) {
-        if self.0[other_row][leader_col] == R64::ZERO {
-            return;
-        }
+// A cursor over a single line of input. Errors give the (1-based) column they happened at.
+struct MachineParser<'a> {
+    line: &'a str,
+    pos: usize,
+}
 
-        let factor = -self.0[other_row][leader_col] / self.0[selected_row][leader_col];
-        let term = self.0[selected_row].clone() * factor;
-        self.0[other_row].add_assign(&term).unwrap();
+impl MachineParser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.line[self.pos..].chars().next()
     }
 
-    fn normalize(&mut self) {
-        self.0.iter_mut().for_each(|row| row.normalize());
+    fn peek_past_whitespace(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.peek()
     }
 
-    fn eliminate_above_leader(&mut self, row: usize) {
-        let Some(leader_col) = self.0[row].leader_col() else {
-            return;
-        };
-
-        for i in 0..row {
-            self.eliminate(row, i, leader_col);
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek().filter(|c| c.is_whitespace()) {
+            self.pos += c.len_utf8();
         }
     }
 
-    fn matrix_mul(&self, rhs: &Self) -> anyhow::Result<Self> {
-        if (self.0.is_empty() || self.0[0].is_empty()) && (rhs.0.is_empty() || rhs.0[0].len() == 1)
-        {
-            return Ok(Self(Vec::new()));
+    fn expect(&mut self, expected: char) -> anyhow::Result<()> {
+        if self.peek_past_whitespace() != Some(expected) {
+            return Err(self.unexpected(&format!("{:?}", expected)));
         }
-
-        if self.0[0].len() != rhs.0.len() {
-            anyhow::bail!(
-                "multiplication of incompatible matrices: lhs width {} vs rhs height {}",
-                self.0[0].len(),
-                rhs.0.len()
-            );
-        }
-
-        let mut new = Self::zeros(self.0.len(), rhs.0[0].len());
-        for i in 0..rhs.0[0].len() {
-            for j in 0..self.0.len() {
-                for k in 0..self.0[0].len() {
-                    new.0[j][i] += self.0[j][k] * rhs.0[k][i];
-                }
-            }
-        }
-        Ok(new)
-    }
-
-    fn append_row(&mut self, r: RowVec) {
-        self.0.push(r);
-    }
-
-    fn remove_row(&mut self, i: usize) {
-        self.0.remove(i);
-    }
-
-    fn height(&self) -> usize {
-        self.0.len()
+        self.pos += 1;
+        Ok(())
     }
 
-    fn width(&self) -> usize {
-        if self.0.is_empty() {
-            0
-        } else {
-            self.0[0].len()
+    // A comma-separated list of numbers between open and close, along with each number's column.
+    fn list(&mut self, open: char, close: char) -> anyhow::Result<Vec<(usize, i64)>> {
+        self.expect(open)?;
+        let mut numbers = vec![self.number()?];
+        while self.peek_past_whitespace() == Some(',') {
+            self.pos += 1;
+            numbers.push(self.number()?);
         }
+        self.expect(close)?;
+        Ok(numbers)
     }
 
-    fn iter(&self) -> impl Iterator<Item = &RowVec> {
-        self.0.iter()
-    }
-
-    fn get_column_copy(&self, c: usize) -> Self {
-        Self::new(self.iter().map(|r| vec![r[c]]).collect())
-    }
-}
-
-impl MulAssign<R64> for Matrix {
-    fn mul_assign(&mut self, rhs: R64) {
-        for row in &mut self.0 {
-            *row *= rhs;
+    fn number(&mut self) -> anyhow::Result<(usize, i64)> {
+        self.skip_whitespace();
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            return Err(self.unexpected("a number"));
         }
-    }
-}
-
-impl Mul<R64> for Matrix {
-    type Output = Self;
 
-    fn mul(mut self, rhs: R64) -> Self::Output {
-        self *= rhs;
-        self
+        let n = self.line[start..self.pos]
+            .parse()
+            .map_err(|e| self.error_at(start, format!("{}", e)))?;
+        Ok((start, n))
     }
-}
 
-impl Index<usize> for Matrix {
-    type Output = RowVec;
-
-    fn index(&self, index: usize) -> &Self::Output {
-        &self.0[index]
+    fn unexpected(&self, expected: &str) -> anyhow::Error {
+        let found = match self.peek() {
+            Some(c) => format!("{:?}", c),
+            None => "the end of the line".to_string(),
+        };
+        self.error(format!("expected {}, found {}", expected, found))
     }
-}
 
-impl IndexMut<usize> for Matrix {
-    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
-        &mut self.0[index]
+    fn error(&self, message: String) -> anyhow::Error {
+        self.error_at(self.pos, message)
     }
-}
 
-impl Index<(usize, usize)> for Matrix {
-    type Output = R64;
-
-    fn index(&self, index: (usize, usize)) -> &Self::Output {
-        &self.0[index.0][index.1]
+    fn error_at(&self, pos: usize, message: String) -> anyhow::Error {
+        anyhow::anyhow!("column {}: {}", pos + 1, message)
     }
 }
 
-impl IndexMut<(usize, usize)> for Matrix {
-    fn index_mut(&mut self, index: (usize, usize)) -> &mut Self::Output {
-        &mut self.0[index.0][index.1]
-    }
-}
+// Bit i is set if light i is on.
+type Lights = u64;
 
-impl IntoIterator for Matrix {
-    type Item = RowVec;
-    type IntoIter = std::vec::IntoIter<Self::Item>;
+// Bit i is set if button i gets pressed.
+type Presses = u64;
 
-    fn into_iter(self) -> Self::IntoIter {
-        self.0.into_iter()
-    }
-}
+type Button = Vec<usize>;
+type Joltage = i64;
 
-impl fmt::Display for Matrix {
-    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        write!(f, "[ ")?;
-        let mut first = true;
-        for v in &self.0 {
-            if !first {
-                write!(f, "\n  ")?;
+pub fn run(options: &Options) {
+    let mut contents = String::new();
+    let (mut writer, contents) = prep_io(&mut contents, 10).unwrap();
+    let machines: Vec<Machine> = contents
+        .iter()
+        .enumerate()
+        .map(|(i, line)| {
+            line.parse()
+                .unwrap_or_else(|e| panic!("invalid input on line {}: {}", i + 1, e))
+        })
+        .collect();
+
+    // the plans are checked before anyone gets to see them
+    let verify = options.verify || options.report || options.export.is_some();
+    let (light_plans, light_times): (Vec<_>, Vec<_>) =
+        part1(&mut writer, &machines, verify).into_iter().unzip();
+    let (joltage_plans, joltage_times): (Vec<_>, Vec<_>) =
+        part2(&mut writer, &machines, verify).into_iter().unzip();
+
+    if options.report {
+        print_plans(&machines, &light_plans, &joltage_plans);
+        print_slowest(&light_times, &joltage_times);
+    }
+    if let Some((mut export_writer, format)) = options.create_export() {
+        match format {
+            "json" => {
+                export_plans_json(&mut export_writer, &machines, &light_plans, &joltage_plans)
             }
-            first = false;
-            write!(f, "{} ", *v)?;
+            _ => panic!("day 10 can't export to {:?} - try json", format),
         }
-        write!(f, "]")?;
-        Ok(())
     }
 }
 
-const VERBOSE: bool = false;
-
-// very slow...
-fn part2<W: Write>(writer: &mut BufWriter<W>, machines: Vec<Machine>) {
-    let mut total = R64::ZERO;
-    for m in &machines {
-        if VERBOSE {
-            println!("{:?}", m.buttons);
-            println!("{:?}", m.joltage_reqs);
-        }
-
-        // make a matrix representing the relationship between joltages and button presses.
-        // For an example scenario, an input of "(1, 3) (4) (2, 4, 5) (0) (3) {4,3,1,8,3,1}" will give a
-        // matrix that initially looks like:
-        //
-        // [ [ 0 0 0 1 0 1 0 ]
-        //   [ 1 0 0 0 0 1 0 ]
-        //   [ 0 0 1 0 0 0 0 ]
-        //   [ 1 0 0 0 1 0 0 ]
-        //   [ 0 1 1 0 0 0 0 ]
-        //   [ 0 0 1 0 0 0 0 ]
-        //
-        // Where each column except the rightmost encodes the effects of one button.
-        let mut mat = Matrix::zeros(m.joltage_reqs.len(), m.buttons.len() + 1);
-        for (i, b) in m.buttons.iter().enumerate() {
-            for c in b {
-                mat[(*c, i)] = R64::ONE;
-            }
-        }
-
-        // finish the matrix from above by putting the joltage values in the last column:
-        //
-        // [ [ 0 0 0 1 0 1 6 ]
-        //   [ 1 0 0 0 0 1 5 ]
-        //   [ 0 0 1 0 0 0 1 ]
-        //   [ 1 0 0 0 1 0 8 ]
-        //   [ 0 1 1 0 0 0 3 ]
-        //   [ 0 0 1 0 0 0 1 ] ]
-        //
-        // This matrix now represents a series of linear equations:
-        //
-        // * row 0:  b3 + b5 = 6
-        // * row 1:  b0 + b5 = 5
-        // * row 2:     b2   = 1
-        // * row 3:  b0 + b4 = 8
-        // * row 4:  b1 + b2 = 3
-        // * row 5:     b2   = 1
-        //
-        // where each bi is the number of presses of button i.
-        for (i, j) in m.joltage_reqs.iter().enumerate() {
-            mat[(i, m.buttons.len())] = R64::from_integer(*j);
-        }
-
-        if VERBOSE {
-            println!("{}", &mat);
-            println!();
-        }
-
-        let original_mat = mat.clone();
-
-        // put the matrix into reduced row echelon form to "solve" it.
-        //
-        // [ [ 1 0 0 0 0 1 5 ]
-        //   [ 0 1 0 0 0 0 2 ]
-        //   [ 0 0 1 0 0 0 1 ]
-        //   [ 0 0 0 1 0 1 6 ]
-        //   [ 0 0 0 0 1 -1 3 ]
-        //   [ 0 0 0 0 0 0 0 ] ]
-        mat.rref();
-        if VERBOSE {
-            println!("{}", &mat);
-            println!();
-        }
-
-        // but there is no unique solution in these problems, so we have to find the columns that
-        // have more than one non-zero value - these represent variables that we'll have to iterate
-        // through to find specific solutions.
-        let iec = inexact_columns(&mat);
-        if VERBOSE {
-            println!("{:?}", iec);
-        }
-
-        // for each new variable, we add a row like [ 0 0 0 0 0 1 0 ], which essentially says "bi = 0".
-        // It's important that we keep this unsorted for now so we know which rows are the ones we
-        // can iterate through.
-        //
-        // [ [ 1 0 0 0 0 1 5 ]
-        //   [ 0 1 0 0 0 0 2 ]
-        //   [ 0 0 1 0 0 0 1 ]
-        //   [ 0 0 0 1 0 1 6 ]
-        //   [ 0 0 0 0 1 -1 3 ]
-        //   [ 0 0 0 0 0 0 0 ]
-        //   [ 0 0 0 0 0 1 0 ] ]
-        for col in &iec {
-            let width = mat.width();
-            mat.append_row(RowVec(vec![R64::ZERO; width]));
-            let row = mat.height() - 1;
-            mat[(row, *col)] = R64::ONE;
-        }
+// Runs solve on every machine, timing each one. Every machine is independent, so with the
+// parallel feature they get spread across threads, but the results always come back in the same
+// order as the machines.
+fn solve_each<T, F>(machines: &[Machine], solve: F) -> Vec<(T, Duration)>
+where
+    T: Send,
+    F: Fn(&Machine) -> T + Sync,
+{
+    let timed = |m: &Machine| {
+        let start = Instant::now();
+        let result = solve(m);
+        (result, start.elapsed())
+    };
 
-        // remove all-zero rows, they are useless
-        //
-        // [ [ 1 0 0 0 0 1 5 ]
-        //   [ 0 1 0 0 0 0 2 ]
-        //   [ 0 0 1 0 0 0 1 ]
-        //   [ 0 0 0 1 0 1 6 ]
-        //   [ 0 0 0 0 1 -1 3 ]
-        //   [ 0 0 0 0 0 1 0 ] ]
-        let mut i = 0;
-        loop {
-            if i >= mat.height() {
-                break;
-            }
-            if mat[i].is_zeros() {
-                mat.remove_row(i);
-                i = 0;
-            } else {
-                i += 1;
-            }
-        }
+    #[cfg(feature = "parallel")]
+    return machines.par_iter().map(timed).collect();
 
-        if mat.width() != mat.height() + 1 {
-            panic!(
-                "matrix should be essentially square but with one extra column for after the = sign. Got height {} and width {}",
-                mat.height(),
-                mat.width()
-            );
-        }
+    #[cfg(not(feature = "parallel"))]
+    machines.iter().map(timed).collect()
+}
 
-        if VERBOSE {
-            println!("{}", &mat);
-            println!();
+// Returns the number of times each button gets pressed on each machine (always 0 or 1), along
+// with how long each machine took.
+fn part1<W: Write>(
+    writer: &mut BufWriter<W>,
+    machines: &[Machine],
+    verify: bool,
+) -> Vec<(RowVec, Duration)> {
+    let plans = solve_each(machines, |m| {
+        let presses = m
+            .min_light_presses()
+            .expect("no way to get the lights into the right state");
+
+        let plan = RowVec::new(
+            (0..m.buttons.len())
+                .map(|i| scalar(((presses >> i) & 1) as i64))
+                .collect(),
+        );
+        if verify && !is_real_lights_solution(&plan, &m.lights_matrix(), m.lights_goal) {
+            panic!("incorrect solution found: {}", &plan);
         }
+        plan
+    });
 
-        let min =
-            find_min_solution(&mat, &m.buttons, &m.joltage_reqs, &original_mat, iec.len()).unwrap();
-        println!("MIN: {}", min);
-        total += min;
-
-        if VERBOSE {
-            println!();
-        }
-    }
+    // summed in machine order, so the result never depends on which thread finished first
+    let total: Scalar = plans.iter().flat_map(|(plan, _)| plan.iter()).sum();
     printwriteln!(writer, "{}", total).unwrap();
+    plans
 }
 
-const DOUBLE_CHECK: bool = false;
-
-fn find_min_solution(
-    mat: &Matrix,
-    buttons: &Vec<Button>,
-    joltage_reqs: &Vec<Joltage>,
-    original_mat: &Matrix,
-    free_vars: usize,
-) -> Option<R64> {
-    // base case - there are no free variables remaining
-    if free_vars == 0 {
-        let mut mat = mat.clone();
-
-        // solve the matrix and check if it's a valid solution for this problem (ie, if it contains
-        // no negative or fractional values)
-        mat.rref();
-        if !is_valid(&mat) {
-            return None;
-        }
+// Returns the number of times each button gets pressed on each machine, along with how long each
+// machine took.
+fn part2<W: Write>(
+    writer: &mut BufWriter<W>,
+    machines: &[Machine],
+    verify: bool,
+) -> Vec<(RowVec, Duration)> {
+    let solutions = solve_each(machines, |m| {
+        trace!("buttons: {:?}", m.buttons);
+        trace!("joltages: {:?}", m.joltage_reqs);
+
+        let mat = m.joltage_matrix();
+        trace!("matrix: {}", &mat);
+
+        // now find the solution that uses the fewest button presses in total, where every button
+        // has to be pressed a whole, non-negative number of times. There are generally infinitely
+        // many rational solutions, so this is an integer linear program.
+        let costs = vec![Scalar::one(); m.buttons.len()];
+        let solution = ilp::minimise(&mat, &costs)
+            .unwrap()
+            .expect("no way to meet the joltage requirements");
+        trace!("presses: {}", &solution.x);
 
         // double-check that this actually is a real solution to the matrix and there's not a bug
         // in the code
-        if DOUBLE_CHECK && !is_real_solution(&mat, original_mat, joltage_reqs) {
-            println!("{}", &mat);
-            panic!("incorrect solution found ^^");
-        }
-        if VERBOSE {
-            println!("{}", &mat);
+        if verify && !is_real_solution(&solution.x, &mat, &m.joltage_reqs) {
+            panic!("incorrect solution found: {}", &solution.x);
         }
 
-        // the final total number of button presses for this solution is the sum of the rightmost
-        // column.
-        let mut total = R64::ZERO;
-        for i in 0..mat.height() {
-            total += mat[(i, mat.width() - 1)];
-        }
-        if VERBOSE {
-            println!("{}", total);
-            println!();
-        }
-        return Some(total);
-    }
+        solution
+    });
 
-    // if we do have free variables left, then pick a relevant row to work with for now and find
-    // its leader column
-    let variable_row = mat.height() - free_vars;
-    let leader_col = mat[variable_row].leader_col().unwrap();
-
-    // for that column, we need to iterate from 0 up to (and including) the magnitude of the
-    // smallest joltage that this column (button) affects.
-    let mut max_req_button_presses = OptionMinMax(None);
-    for j in &buttons[leader_col] {
-        max_req_button_presses = max_req_button_presses.min(joltage_reqs[*j]);
-    }
-    let max_req_button_presses = max_req_button_presses.0.unwrap();
-
-    // iterate and recurse to "count" up all the possible solutions with these free variables, and
-    // keep track of the minimum number of button presses per solution as we go.
-    let mut min = None;
-    let mut mat = mat.clone();
-    for i in 0..=max_req_button_presses {
-        let width = mat.width() - 1;
-        mat[(variable_row, width)] = R64::from_integer(i); // set the free variable to a sample value
-        let m = find_min_solution(&mat, buttons, joltage_reqs, original_mat, free_vars - 1);
-
-        // TODO: make OptionMinMax able to handle this scenario more nicely
-        if min.is_none() || (m.is_some() && m.unwrap() < min.unwrap()) {
-            min = m;
-        }
+    // summed in machine order, so the result never depends on which thread finished first
+    let mut total = Scalar::zero();
+    let mut plans = Vec::with_capacity(solutions.len());
+    for (i, (solution, elapsed)) in solutions.into_iter().enumerate() {
+        debug!("machine {}: {} presses", i + 1, &solution.objective);
+        total += solution.objective;
+        plans.push((solution.x, elapsed));
     }
-
-    min
+    printwriteln!(writer, "{}", total).unwrap();
+    plans
 }
 
-fn is_valid(m: &Matrix) -> bool {
-    fold_while(m.iter(), true, |_, row| {
-        let r = fold_while(row.iter(), true, |_, v| {
-            let r = *v < R64::ZERO || *v.denom() != 1;
-            (!r, !r)
-        });
-        (r, r)
-    })
+fn is_real_solution(presses: &RowVec, original_mat: &Matrix, joltage_reqs: &[Joltage]) -> bool {
+    press_effects(presses, original_mat)
+        == Matrix::new(joltage_reqs.iter().map(|j| vec![scalar(*j)]).collect())
 }
 
-fn inexact_columns(m: &Matrix) -> Vec<usize> {
-    let mut inexact = Vec::new();
-    for c in 0..(m.width() - 1) {
-        if col_is_inexact(m, c) {
-            inexact.push(c);
-        }
-    }
-    inexact
-}
-
-fn col_is_inexact(m: &Matrix, c: usize) -> bool {
-    m.iter().filter(|v| v[c] != R64::ZERO).count() != 1
+// Each light gets toggled once for every press of a button wired to it, so the lights that end up
+// on are the ones that get toggled an odd number of times. lights_mat comes from lights_matrix.
+fn is_real_lights_solution(presses: &RowVec, lights_mat: &Matrix, lights_goal: Lights) -> bool {
+    let toggles = press_effects(presses, lights_mat);
+    (0..toggles.height()).all(|light| {
+        let on = Integer::is_odd(&toggles[(light, 0)].to_integer());
+        on == (lights_goal & (1 << light) != 0)
+    })
 }
 
-fn is_real_solution(mat: &Matrix, original_mat: &Matrix, joltage_reqs: &[Joltage]) -> bool {
-    // extract the rightmost column of the solution into its own column-vector, then add an extra 0
-    // entry at the bottom.
-    let mut solution = mat.get_column_copy(mat.width() - 1);
-    solution.append_row(RowVec(vec![R64::ZERO]));
+// The column-vector of joltages that the presses produce.
+fn press_effects(presses: &RowVec, original_mat: &Matrix) -> Matrix {
+    // turn the solution into a column-vector, then add an extra 0 entry at the bottom.
+    let mut solution = Matrix::new(presses.iter().cloned().map(|p| vec![p]).collect());
+    solution.append_row(RowVec::zeros(1));
 
     // we expect the result of matrix-multiplying the original matrix by the solution column-vector
     // to equal a column-vector of the joltage requirements. For example, for the original matrix
@@ -757,27 +540,120 @@ fn is_real_solution(mat: &Matrix, original_mat: &Matrix, joltage_reqs: &[Joltage
     //           [ 3 ]
     //           [ 1 ] ]
     original_mat.matrix_mul(&solution).unwrap()
-        == Matrix::new(
-            joltage_reqs
-                .iter()
-                .map(|j| vec![R64::from_integer(*j)])
-                .collect(),
-        )
 }
 
-// TODO: move to aoclib-rs
-fn fold_while<I, B, F>(it: I, init: B, mut f: F) -> B
-where
-    I: Iterator,
-    F: FnMut(B, I::Item) -> (B, bool),
-{
-    let mut b = init;
-    for v in it {
-        let (new_b, should_continue) = f(b, v);
-        b = new_b;
-        if !should_continue {
-            break;
+fn join<T: ToString>(values: &[T], sep: &str) -> String {
+    values
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>()
+        .join(sep)
+}
+
+// Prints how many times each button gets pressed on each machine, for both parts, eg:
+//
+// machine 1: [.##.] {3,5,4,7}
+//   button  lights  joltage
+//   (3)          0        1
+//   (1,3)        1        5
+//   (2)          0        0
+//   (2,3)        1        1
+//   (0,2)        0        3
+//   (0,1)        0        0
+//   total        2       10
+fn print_plans(machines: &[Machine], light_plans: &[RowVec], joltage_plans: &[RowVec]) {
+    for (i, m) in machines.iter().enumerate() {
+        println!(
+            "machine {}: {} {{{}}}",
+            i + 1,
+            m.lights_goal_str(),
+            join(&m.joltage_reqs, ",")
+        );
+        let labels: Vec<_> = m
+            .buttons
+            .iter()
+            .map(|b| format!("({})", join(b, ",")))
+            .collect();
+        let width = labels.iter().map(|l| l.len()).max().unwrap_or(0).max(6);
+        println!("  {:<width$}  {:>6}  {:>7}", "button", "lights", "joltage");
+        for (b, label) in labels.iter().enumerate() {
+            println!(
+                "  {:<width$}  {:>6}  {:>7}",
+                label, light_plans[i][b], joltage_plans[i][b]
+            );
         }
+        println!(
+            "  {:<width$}  {:>6}  {:>7}",
+            "total",
+            light_plans[i].iter().sum::<Scalar>(),
+            joltage_plans[i].iter().sum::<Scalar>()
+        );
     }
-    b
 }
+
+// A JSON array with one object per machine, giving how many times each button gets pressed for
+// each part.
+fn export_plans_json<W: Write>(
+    writer: &mut BufWriter<W>,
+    machines: &[Machine],
+    light_plans: &[RowVec],
+    joltage_plans: &[RowVec],
+) {
+    writeln!(writer, "[").unwrap();
+    for (i, m) in machines.iter().enumerate() {
+        let buttons: Vec<_> = m
+            .buttons
+            .iter()
+            .map(|b| format!("[{}]", join(b, ", ")))
+            .collect();
+        writeln!(writer, "  {{").unwrap();
+        writeln!(writer, "    \"lights\": \"{}\",", m.lights_goal_str()).unwrap();
+        writeln!(writer, "    \"buttons\": [{}],", buttons.join(", ")).unwrap();
+        writeln!(
+            writer,
+            "    \"joltages\": [{}],",
+            join(&m.joltage_reqs, ", ")
+        )
+        .unwrap();
+        writeln!(
+            writer,
+            "    \"light_presses\": [{}],",
+            join(&light_plans[i], ", ")
+        )
+        .unwrap();
+        writeln!(
+            writer,
+            "    \"joltage_presses\": [{}]",
+            join(&joltage_plans[i], ", ")
+        )
+        .unwrap();
+        let comma = if i + 1 < machines.len() { "," } else { "" };
+        writeln!(writer, "  }}{}", comma).unwrap();
+    }
+    writeln!(writer, "]").unwrap();
+}
+
+// Prints the machines that took longest to solve (counting both parts), slowest first, eg:
+//
+// slowest machines:
+// machine     part 1     part 2
+//       3     1.21µs   311.53µs
+//       2      845ns   204.17µs
+//       1      922ns    58.09µs
+fn print_slowest(light_times: &[Duration], joltage_times: &[Duration]) {
+    let mut machines: Vec<usize> = (0..light_times.len()).collect();
+    machines.sort_by_key(|&i| std::cmp::Reverse(light_times[i] + joltage_times[i]));
+
+    println!("slowest machines:");
+    println!("machine     part 1     part 2");
+    for i in machines.into_iter().take(SLOWEST_SHOWN) {
+        println!(
+            "{:>7}  {:>9}  {:>9}",
+            i + 1,
+            format!("{:.2?}", light_times[i]),
+            format!("{:.2?}", joltage_times[i])
+        );
+    }
+}
+
+const SLOWEST_SHOWN: usize = 10;
@@ -4,51 +4,313 @@ use std::{
 };
 
 use aoclib_rs::{prep_io, printwriteln};
+use num_bigint::{BigUint, RandBigInt};
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use tracing::{debug, warn};
 
-type Device = String;
-type Output = String;
-type Outputs = Vec<Output>;
-type Graph = HashMap<Device, Outputs>;
+use super::Options;
 
-pub fn run() {
-    let mut contents = String::new();
-    let (mut writer, contents) = prep_io(&mut contents, 11).unwrap();
+type DeviceId = u32;
 
-    let graph: Graph = contents
-        .iter()
-        .map(|line| {
+// The devices, with their names interned as dense ids, and their outputs in compressed sparse row
+// form: the outputs of device d are outputs[offsets[d]..offsets[d + 1]]. Every device mentioned in
+// the input comes before any that were only added because they were asked about.
+struct Graph {
+    names: Vec<String>,
+    ids: HashMap<String, DeviceId>,
+    in_input: usize,
+    offsets: Vec<usize>,
+    outputs: Vec<DeviceId>,
+}
+
+impl Graph {
+    // asked_about are the devices that are going to be looked up by name, which get ids even if
+    // the input doesn't mention them (they just won't have any inputs or outputs).
+    fn parse(contents: &[&str], asked_about: &[&str]) -> Self {
+        let mut graph = Graph {
+            names: Vec::new(),
+            ids: HashMap::new(),
+            in_input: 0,
+            offsets: Vec::new(),
+            outputs: Vec::new(),
+        };
+
+        let mut edges: Vec<(DeviceId, DeviceId)> = Vec::new();
+        for line in contents {
             let mut line_split = line.split(": ");
-            let device = line_split.next().unwrap();
+            let device = graph.intern(line_split.next().unwrap());
 
             let outputs_str = line_split.next().unwrap();
-            let outputs_split = outputs_str.split(" ");
-            let outputs: Outputs = outputs_split.map(|o| o.to_owned()).collect();
+            for output in outputs_str.split(" ") {
+                edges.push((device, graph.intern(output)));
+            }
+        }
+        graph.in_input = graph.names.len();
+        for name in asked_about {
+            graph.intern(name);
+        }
+
+        // a counting sort by device, which keeps each device's outputs in the order they were
+        // listed
+        graph.offsets = vec![0; graph.names.len() + 1];
+        for &(device, _) in &edges {
+            graph.offsets[device as usize + 1] += 1;
+        }
+        for i in 1..graph.offsets.len() {
+            graph.offsets[i] += graph.offsets[i - 1];
+        }
+        let mut next = graph.offsets.clone();
+        graph.outputs = vec![0; edges.len()];
+        for (device, output) in edges {
+            graph.outputs[next[device as usize]] = output;
+            next[device as usize] += 1;
+        }
+
+        graph
+    }
+
+    fn intern(&mut self, name: &str) -> DeviceId {
+        if let Some(&id) = self.ids.get(name) {
+            return id;
+        }
+        let id = DeviceId::try_from(self.names.len()).expect("too many devices");
+        self.names.push(name.to_owned());
+        self.ids.insert(name.to_owned(), id);
+        id
+    }
+
+    fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    // Only for devices that were in the input or asked about when parsing.
+    fn id(&self, name: &str) -> DeviceId {
+        self.ids[name]
+    }
+
+    fn ids(&self, names: &[&str]) -> Vec<DeviceId> {
+        names.iter().map(|name| self.id(name)).collect()
+    }
+
+    fn name(&self, device: DeviceId) -> &str {
+        &self.names[device as usize]
+    }
+
+    fn outputs(&self, device: DeviceId) -> &[DeviceId] {
+        let device = device as usize;
+        &self.outputs[self.offsets[device]..self.offsets[device + 1]]
+    }
+
+    fn in_input(&self, device: DeviceId) -> bool {
+        (device as usize) < self.in_input
+    }
+}
+
+pub fn run(options: &Options) {
+    let mut contents = String::new();
+    let (mut writer, contents) = prep_io(&mut contents, 11).unwrap();
+
+    let mut asked_about = vec!["you", "svr", "out", "dac", "fft"];
+    if let Some(query) = &options.query {
+        asked_about.extend(
+            [&query.from, &query.to]
+                .into_iter()
+                .chain(&query.via)
+                .map(String::as_str),
+        );
+    }
+    let graph = Graph::parse(&contents, &asked_about);
 
-            (device.to_owned(), outputs)
-        })
+    // (from, to, via) for the query, if there is one
+    let query = options.query.as_ref().map(|query| {
+        for device in [&query.from, &query.to].into_iter().chain(&query.via) {
+            if !graph.in_input(graph.id(device)) {
+                warn!("{} doesn't appear anywhere in the input", device);
+            }
+        }
+
+        // each waypoint gets its own bit, so a repeated one could never be fully visited
+        let mut via: Vec<&str> = Vec::new();
+        for device in &query.via {
+            if !via.contains(&device.as_str()) {
+                via.push(device);
+            }
+        }
+        (query.from.as_str(), query.to.as_str(), via)
+    });
+
+    match &query {
+        // a query only cares about its own devices, so the puzzle's answers are skipped, along
+        // with any problems in the parts of the graph they'd need
+        Some((from, to, _)) => check_graph(&graph, &[from], to),
+        None => {
+            check_graph(&graph, &["you", "svr"], "out");
+            part1(&mut writer, &graph);
+            part2(&mut writer, &graph);
+        }
+    }
+
+    if let Some((from, to, via)) = &query {
+        let paths = count_paths(&graph, from, to, via)
+            .unwrap_or_else(|e| panic!("can't count paths: {}", e));
+        println!(
+            "{} paths from {} to {}{}",
+            paths,
+            from,
+            to,
+            if via.is_empty() {
+                String::new()
+            } else {
+                format!(" via {}", via.join(", "))
+            }
+        );
+    }
+
+    if options.list_paths.is_some() || options.sample_paths.is_some() {
+        let mut rng = match options.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        match &query {
+            Some((from, to, via)) => print_paths(&graph, from, to, via, options, &mut rng),
+            None => {
+                print_paths(&graph, "you", "out", &[], options, &mut rng);
+                print_paths(&graph, "svr", "out", &["dac", "fft"], options, &mut rng);
+            }
+        }
+    }
+
+    if let Some((mut export_writer, format)) = options.create_export() {
+        match format {
+            "dot" => export_dot(&mut export_writer, &graph),
+            _ => panic!("day 11 can't export to {:?} - try dot", format),
+        }
+    }
+}
+
+// Warns about devices that are listed as outputs but don't have any outputs of their own (other
+// than the end), since those are dead ends, and about devices that can't be reached from any of
+// the starts.
+fn check_graph(graph: &Graph, starts: &[&str], end: &str) {
+    let has_outputs = |device: DeviceId| !graph.outputs(device).is_empty();
+    let end = graph.id(end);
+    let starts = graph.ids(starts);
+    let starts_str = || {
+        let names: Vec<_> = starts.iter().map(|&s| graph.name(s)).collect();
+        names.join(" or ")
+    };
+
+    // everything in the input is either listed with its outputs, or is one of those outputs
+    let in_input = (0..graph.len() as DeviceId).filter(|&d| graph.in_input(d));
+    let mut dangling: Vec<_> = in_input
+        .clone()
+        .filter(|&d| d != end && !has_outputs(d))
+        .map(|d| graph.name(d))
+        .collect();
+    dangling.sort();
+    warn_devices(
+        "with no outputs listed, so no paths lead through them",
+        &dangling,
+    );
+
+    let mut reachable = vec![false; graph.len()];
+    let mut to_visit = starts.clone();
+    while let Some(device) = to_visit.pop() {
+        if !reachable[device as usize] {
+            reachable[device as usize] = true;
+            to_visit.extend(graph.outputs(device));
+        }
+    }
+
+    for &start in &starts {
+        if !has_outputs(start) {
+            warn!("start {} has no outputs listed", graph.name(start));
+        }
+    }
+    if !reachable[end as usize] {
+        warn!(
+            "end {} can't be reached from {}",
+            graph.name(end),
+            starts_str()
+        );
+    }
+
+    let mut unreachable: Vec<_> = in_input
+        .filter(|&d| has_outputs(d) && !reachable[d as usize])
+        .map(|d| graph.name(d))
         .collect();
+    unreachable.sort();
+    warn_devices(
+        &format!("that can't be reached from {}", starts_str()),
+        &unreachable,
+    );
+}
 
-    part1(&mut writer, &graph);
-    part2(&mut writer, &graph);
+// Generated graphs can have thousands of devices with the same problem, so this warns about them
+// all in one line, only naming the first few. The rest are listed with -v.
+fn warn_devices(problem: &str, devices: &[&str]) {
+    const NAMED: usize = 5;
+
+    if devices.is_empty() {
+        return;
+    }
+    let more = if devices.len() > NAMED {
+        format!(", and {} more (-v lists them)", devices.len() - NAMED)
+    } else {
+        String::new()
+    };
+    warn!(
+        "{} device{} {}: {}{}",
+        devices.len(),
+        if devices.len() == 1 { "" } else { "s" },
+        problem,
+        devices[..devices.len().min(NAMED)].join(", "),
+        more
+    );
+    for device in devices.iter().skip(NAMED) {
+        debug!("also {}: {}", problem, device);
+    }
 }
 
 fn part1<W: Write>(writer: &mut BufWriter<W>, graph: &Graph) {
-    let mut intermediate_paths = HashMap::new();
-    let (_, _, _, paths) = dfs(graph, "you", "out", &mut intermediate_paths);
+    let paths = count_paths(graph, "you", "out", &[])
+        .unwrap_or_else(|e| panic!("can't count the paths for part 1: {}", e));
     printwriteln!(writer, "{}", paths).unwrap();
 }
 
-type PathsIfAncestorsContainNeither = i64;
-type PathsIfAncestorsContainDac = i64;
-type PathsIfAncestorsContainFft = i64;
-type PathsIfAncestorsContainBoth = i64;
+// Counts the paths from start to end which visit every one of the waypoints, in any order. Fails
+// if there's a cycle reachable from start, since there could be infinitely many paths, or if there
+// are too many waypoints for the size of the graph (see MAX_PATH_COUNTS).
+fn count_paths(
+    graph: &Graph,
+    start: &str,
+    end: &str,
+    waypoints: &[&str],
+) -> anyhow::Result<BigUint> {
+    let start = graph.id(start);
+    let mut counts = path_counts(graph, start, graph.id(end), &graph.ids(waypoints))?;
+    Ok(counts.swap_remove(start as usize).swap_remove(0))
+}
+
+// Which of the waypoints have been visited, as a bitmask: bit i is set if waypoints[i] has been.
+type Visited = usize;
+
+// For each possible set of waypoints visited by a node's ancestors (indexed by Visited), the
+// number of paths from the node to the end which complete the set of waypoints. These can get
+// exponentially big in dense graphs.
+type PathCounts = Vec<BigUint>;
 
-type DfsResult = (
-    PathsIfAncestorsContainNeither,
-    PathsIfAncestorsContainDac,
-    PathsIfAncestorsContainFft,
-    PathsIfAncestorsContainBoth,
-);
+// Every device reachable from the start gets 2^waypoints path counts, so the memory needed doubles
+// with every waypoint. This caps the total, which is several hundred megabytes even while the
+// counts are small.
+const MAX_PATH_COUNTS: usize = 1 << 24;
+
+fn waypoint_bit(waypoints: &[DeviceId], node: DeviceId) -> Visited {
+    waypoints
+        .iter()
+        .position(|&w| w == node)
+        .map_or(0, |i| 1 << i)
+}
 
 // Example scenario (note that in the actual problem, nodes (like dac and fft) cannot be repeated
 // multiple times in the same path, but this implementation also handles a more general form of the
@@ -76,7 +338,9 @@ type DfsResult = (
 // │out                                │
 // └───────────────────────────────────┘
 //
-// In this diagram, the nodes would output the following DfsResults:
+// With waypoints dac and fft (so dac is bit 0 and fft is bit 1), the nodes would get the
+// following PathCounts - ie. the number of paths if the ancestors contain (neither, dac, fft,
+// both):
 //
 // ╭─────┬─────────╮
 // │svr  │(4 5 5 7)│
@@ -114,63 +378,387 @@ type DfsResult = (
 // least one fft, and there are 7 paths from svr to out in total.
 //
 // Credit to https://diagon.arthursonzogni.com/ for the unicode diagrams.
-fn dfs(graph: &Graph, start: &str, end: &str, paths: &mut HashMap<String, DfsResult>) -> DfsResult {
-    if start == end {
-        // because we know end != "dac" and end != "fft"
-        return (0, 0, 0, 1);
-    }
-
-    if let Some(paths) = paths.get(start) {
-        return *paths;
-    }
-
-    let (
-        mut total_if_ancestors_contain_neither,
-        mut total_if_ancestors_contain_dac,
-        mut total_if_ancestors_contain_fft,
-        mut total_if_ancestors_contain_both,
-    ) = (0, 0, 0, 0);
-    for output in graph.get(start).unwrap() {
-        let (
-            paths_if_ancestors_contain_neither,
-            paths_if_ancestors_contain_dac,
-            paths_if_ancestors_contain_fft,
-            paths_if_ancestors_contain_both,
-        ) = dfs(graph, output, end, paths);
-
-        total_if_ancestors_contain_neither += if start == "dac" {
-            paths_if_ancestors_contain_dac
-        } else if start == "fft" {
-            paths_if_ancestors_contain_fft
+//
+// The counts are worked out without any recursion, so that long chains of devices can't overflow
+// the stack: first the devices reachable from start are put in topological order with Kahn's
+// algorithm, then they're counted in reverse, so every device's outputs are always counted before
+// the device itself.
+//
+// The result is indexed by DeviceId. Devices that can't be reached from start get no counts at all
+// (an empty PathCounts).
+fn path_counts(
+    graph: &Graph,
+    start: DeviceId,
+    end: DeviceId,
+    waypoints: &[DeviceId],
+) -> anyhow::Result<Vec<PathCounts>> {
+    // the end is where paths stop, so anything after it doesn't matter. Devices with no outputs
+    // listed are dead ends.
+    let outputs = |device: DeviceId| -> &[DeviceId] {
+        if device == end {
+            &[]
         } else {
-            paths_if_ancestors_contain_neither
-        };
+            graph.outputs(device)
+        }
+    };
 
-        total_if_ancestors_contain_dac += paths_if_ancestors_contain_dac;
-        total_if_ancestors_contain_fft += paths_if_ancestors_contain_fft;
+    let mut reachable = vec![false; graph.len()];
+    let mut reachable_count = 0;
+    let mut to_visit = vec![start];
+    while let Some(device) = to_visit.pop() {
+        if !reachable[device as usize] {
+            reachable[device as usize] = true;
+            reachable_count += 1;
+            to_visit.extend(outputs(device));
+        }
+    }
 
-        total_if_ancestors_contain_both += paths_if_ancestors_contain_both;
+    if waypoints.len() >= Visited::BITS as usize
+        || reachable_count > MAX_PATH_COUNTS >> waypoints.len()
+    {
+        anyhow::bail!(
+            "too many waypoints: {} devices with 2^{} path counts each would be more than {}",
+            reachable_count,
+            waypoints.len(),
+            MAX_PATH_COUNTS
+        );
     }
 
-    if start == "dac" {
-        total_if_ancestors_contain_fft = total_if_ancestors_contain_both;
-    } else if start == "fft" {
-        total_if_ancestors_contain_dac = total_if_ancestors_contain_both;
+    // only reachable devices have inputs counted, so the rest stay at zero
+    let mut inputs_left: Vec<u32> = vec![0; graph.len()];
+    for device in 0..graph.len() as DeviceId {
+        if reachable[device as usize] {
+            for &output in outputs(device) {
+                inputs_left[output as usize] += 1;
+            }
+        }
     }
 
-    let result = (
-        total_if_ancestors_contain_neither,
-        total_if_ancestors_contain_dac,
-        total_if_ancestors_contain_fft,
-        total_if_ancestors_contain_both,
-    );
+    let mut ready: Vec<DeviceId> = Vec::new();
+    if inputs_left[start as usize] == 0 {
+        ready.push(start);
+    }
+    let mut order = Vec::with_capacity(reachable_count);
+    while let Some(device) = ready.pop() {
+        order.push(device);
+        for &output in outputs(device) {
+            let left = &mut inputs_left[output as usize];
+            *left -= 1;
+            if *left == 0 {
+                ready.push(output);
+            }
+        }
+    }
+    if order.len() < reachable_count {
+        let cycle: Vec<_> = find_cycle(&inputs_left, outputs)
+            .into_iter()
+            .map(|d| graph.name(d))
+            .collect();
+        anyhow::bail!("cycle found: {}", cycle.join(" -> "));
+    }
+
+    let all: Visited = (1 << waypoints.len()) - 1;
+    let mut counts: Vec<PathCounts> = vec![Vec::new(); graph.len()];
+    for &device in order.iter().rev() {
+        let own = waypoint_bit(waypoints, device);
+        let device_counts = if device == end {
+            (0..=all)
+                .map(|visited| BigUint::from(u8::from(visited | own == all)))
+                .collect()
+        } else {
+            // whatever the ancestors visited, the paths through here have visited device too
+            let mut device_counts = vec![BigUint::ZERO; all + 1];
+            for &output in outputs(device) {
+                let output_counts = &counts[output as usize];
+                for (visited, total) in device_counts.iter_mut().enumerate() {
+                    *total += &output_counts[visited | own];
+                }
+            }
+            device_counts
+        };
+        counts[device as usize] = device_counts;
+    }
 
-    paths.insert(start.to_owned(), result);
-    result
+    Ok(counts)
+}
+
+// When Kahn's algorithm gets stuck, every device it didn't get to still has an input from another
+// one it didn't get to, so following inputs backwards from any of them has to go round in a circle
+// eventually. Returns that circle in the direction of the outputs, starting and ending at the same
+// device.
+fn find_cycle<'a, F>(inputs_left: &[u32], outputs: F) -> Vec<DeviceId>
+where
+    F: Fn(DeviceId) -> &'a [DeviceId],
+{
+    let stuck = |device: DeviceId| inputs_left[device as usize] > 0;
+
+    let mut inputs: Vec<Option<DeviceId>> = vec![None; inputs_left.len()];
+    for device in (0..inputs_left.len() as DeviceId).filter(|&d| stuck(d)) {
+        for &output in outputs(device) {
+            if stuck(output) {
+                inputs[output as usize] = Some(device);
+            }
+        }
+    }
+
+    // where each device is on the path so far, so long cycles don't take quadratic time to spot
+    let mut position: Vec<Option<usize>> = vec![None; inputs_left.len()];
+    let first = (0..inputs_left.len() as DeviceId)
+        .find(|&d| stuck(d))
+        .unwrap();
+    let mut path = vec![first];
+    position[first as usize] = Some(0);
+    loop {
+        let input = inputs[*path.last().unwrap() as usize].unwrap();
+        if let Some(i) = position[input as usize] {
+            let mut cycle = path.split_off(i);
+            cycle.reverse();
+            cycle.push(cycle[0]);
+            return cycle;
+        }
+        position[input as usize] = Some(path.len());
+        path.push(input);
+    }
 }
 
 fn part2<W: Write>(writer: &mut BufWriter<W>, graph: &Graph) {
-    let mut intermediate_paths = HashMap::new();
-    let (paths, _, _, _) = dfs(graph, "svr", "out", &mut intermediate_paths);
+    let paths = count_paths(graph, "svr", "out", &["dac", "fft"])
+        .unwrap_or_else(|e| panic!("can't count the paths for part 2: {}", e));
     printwriteln!(writer, "{}", paths).unwrap();
 }
+
+// Prints the paths asked for by --list-paths and --sample-paths, eg:
+//
+// first 2 of 5 paths from you to out:
+//   you -> bbb -> ddd -> ggg -> out
+//   you -> bbb -> eee -> out
+// 2 random paths from you to out:
+//   you -> ccc -> fff -> out
+//   you -> bbb -> eee -> out
+fn print_paths<R: Rng>(
+    graph: &Graph,
+    start: &str,
+    end: &str,
+    waypoints: &[&str],
+    options: &Options,
+    rng: &mut R,
+) {
+    let (start_name, end_name) = (start, end);
+    let (start, end, waypoint_ids) = (graph.id(start), graph.id(end), graph.ids(waypoints));
+    let counts = path_counts(graph, start, end, &waypoint_ids).unwrap();
+    let total = &counts[start as usize][0];
+    let print_path = |path: Vec<DeviceId>| {
+        let names: Vec<_> = path.into_iter().map(|d| graph.name(d)).collect();
+        println!("  {}", names.join(" -> "));
+    };
+    let via = if waypoints.is_empty() {
+        String::new()
+    } else {
+        format!(" via {}", waypoints.join(", "))
+    };
+
+    if let Some(limit) = options.list_paths {
+        let paths = list_paths(graph, &counts, start, end, &waypoint_ids, limit);
+        println!(
+            "first {} of {} paths from {} to {}{}:",
+            paths.len(),
+            total,
+            start_name,
+            end_name,
+            via
+        );
+        for path in paths {
+            print_path(path);
+        }
+    }
+
+    if let Some(samples) = options.sample_paths {
+        if *total == BigUint::ZERO {
+            println!(
+                "no paths from {} to {}{} to pick from",
+                start_name, end_name, via
+            );
+            return;
+        }
+
+        println!(
+            "{} random paths from {} to {}{}:",
+            samples, start_name, end_name, via
+        );
+        for _ in 0..samples {
+            print_path(sample_path(graph, &counts, start, end, &waypoint_ids, rng));
+        }
+    }
+}
+
+// A device's outputs which still have paths to the end that complete the waypoints, given that
+// the devices so far (including this one) have visited the given waypoints. Sorted by name.
+fn viable_outputs(
+    graph: &Graph,
+    counts: &[PathCounts],
+    device: DeviceId,
+    end: DeviceId,
+    visited: Visited,
+) -> Vec<DeviceId> {
+    if device == end {
+        return Vec::new();
+    }
+
+    let mut outputs: Vec<DeviceId> = graph
+        .outputs(device)
+        .iter()
+        .copied()
+        .filter(|&o| counts[o as usize][visited] > BigUint::ZERO)
+        .collect();
+    outputs.sort_by_key(|&o| graph.name(o));
+    outputs.dedup();
+    outputs
+}
+
+// Up to limit of the paths from start to end which visit all the waypoints, in lexicographic
+// order. The counts (from path_counts) are used to skip over anything that can't lead to a path,
+// so this only ever looks at devices which are on at least one of the paths it returns.
+fn list_paths(
+    graph: &Graph,
+    counts: &[PathCounts],
+    start: DeviceId,
+    end: DeviceId,
+    waypoints: &[DeviceId],
+    limit: usize,
+) -> Vec<Vec<DeviceId>> {
+    let mut paths = Vec::new();
+    if limit == 0 || counts[start as usize][0] == BigUint::ZERO {
+        return paths;
+    }
+
+    // one entry per device on the current path: what it's visited, and the outputs still to try
+    // (in reverse, so the next one is at the end)
+    let mut path = vec![start];
+    let visited = waypoint_bit(waypoints, start);
+    let mut stack = vec![(
+        visited,
+        reversed(viable_outputs(graph, counts, start, end, visited)),
+    )];
+    while let Some((visited, outputs)) = stack.last_mut() {
+        if *path.last().unwrap() == end {
+            paths.push(path.clone());
+            if paths.len() == limit {
+                break;
+            }
+        }
+
+        match outputs.pop() {
+            Some(output) => {
+                let visited = *visited | waypoint_bit(waypoints, output);
+                path.push(output);
+                stack.push((
+                    visited,
+                    reversed(viable_outputs(graph, counts, output, end, visited)),
+                ));
+            }
+            None => {
+                path.pop();
+                stack.pop();
+            }
+        }
+    }
+
+    paths
+}
+
+fn reversed<T>(mut v: Vec<T>) -> Vec<T> {
+    v.reverse();
+    v
+}
+
+// One of the paths from start to end which visit all the waypoints, picked uniformly at random.
+// At each device, every output is picked with probability proportional to how many of the paths
+// go through it, which works out the same as picking from all the paths at once. There has to be
+// at least one path.
+fn sample_path<R: Rng>(
+    graph: &Graph,
+    counts: &[PathCounts],
+    start: DeviceId,
+    end: DeviceId,
+    waypoints: &[DeviceId],
+    rng: &mut R,
+) -> Vec<DeviceId> {
+    let mut path = vec![start];
+    let mut visited = waypoint_bit(waypoints, start);
+    while *path.last().unwrap() != end {
+        let outputs = viable_outputs(graph, counts, *path.last().unwrap(), end, visited);
+        let total: BigUint = outputs.iter().map(|&o| &counts[o as usize][visited]).sum();
+
+        let mut pick = rng.gen_biguint_below(&total);
+        let next = outputs
+            .into_iter()
+            .find(|&o| {
+                let paths = &counts[o as usize][visited];
+                if pick < *paths {
+                    true
+                } else {
+                    pick -= paths;
+                    false
+                }
+            })
+            .unwrap();
+
+        visited |= waypoint_bit(waypoints, next);
+        path.push(next);
+    }
+    path
+}
+
+// A Graphviz graph of all the devices, with the starts (you and svr) in blue, the end (out) in
+// green and the waypoints (dac and fft) in orange. Like the diagram above path_counts, every
+// device is labelled with its path counts: the number of paths to out for part 1, and the
+// (neither, dac, fft, both) counts for part 2. Devices that can't be reached from you or svr
+// don't get the corresponding counts.
+fn export_dot<W: Write>(writer: &mut BufWriter<W>, graph: &Graph) {
+    let out = graph.id("out");
+    let part1_counts = path_counts(graph, graph.id("you"), out, &[]).unwrap();
+    let part2_counts =
+        path_counts(graph, graph.id("svr"), out, &graph.ids(&["dac", "fft"])).unwrap();
+
+    let mut devices: Vec<DeviceId> = (0..graph.len() as DeviceId)
+        .filter(|&d| graph.in_input(d))
+        .collect();
+    devices.sort_by_key(|&d| graph.name(d));
+
+    writeln!(writer, "digraph devices {{").unwrap();
+    writeln!(writer, "    node [shape=box];").unwrap();
+    for &device in &devices {
+        let name = graph.name(device);
+        let mut label = name.to_string();
+        let counts = &part1_counts[device as usize];
+        if !counts.is_empty() {
+            label += &format!("\\npart 1: {}", counts[0]);
+        }
+        let counts = &part2_counts[device as usize];
+        if !counts.is_empty() {
+            let counts: Vec<_> = counts.iter().map(|c| c.to_string()).collect();
+            label += &format!("\\npart 2: ({})", counts.join(" "));
+        }
+
+        let colour = match name {
+            "you" | "svr" => ", style=filled, fillcolor=lightblue",
+            "out" => ", style=filled, fillcolor=palegreen",
+            "dac" | "fft" => ", style=filled, fillcolor=orange",
+            _ => "",
+        };
+        writeln!(writer, "    \"{}\" [label=\"{}\"{}];", name, label, colour).unwrap();
+    }
+
+    for &device in &devices {
+        for &output in graph.outputs(device) {
+            writeln!(
+                writer,
+                "    \"{}\" -> \"{}\";",
+                graph.name(device),
+                graph.name(output)
+            )
+            .unwrap();
+        }
+    }
+    writeln!(writer, "}}").unwrap();
+}
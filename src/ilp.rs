@@ -0,0 +1,423 @@
+// Exact integer linear programming over rationals: branch and bound on top of a simplex solver for
+// the LP relaxations.
+// see linalg::Scalar
+#![allow(clippy::clone_on_copy, clippy::op_ref)]
+
+use num_traits::{One, Signed, Zero};
+
+use crate::linalg::{Matrix, RowVec, Scalar};
+
+/// An optimal solution: the value of the objective, and the variables that achieve it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Solution {
+    pub objective: Scalar,
+    pub x: RowVec,
+}
+
+/// Minimises `costs · x` subject to `A x = b`, `x >= 0` and every `x` being an integer, where
+/// `constraints` is the augmented matrix `[ A | b ]`. Returns None if there's no solution at all.
+///
+/// This is exact: every LP relaxation is solved exactly over rationals, and a branch is only
+/// pruned once its relaxation proves it can't beat the best integer solution found so far, so
+/// the result is guaranteed optimal.
+pub fn minimise(constraints: &Matrix, costs: &[Scalar]) -> anyhow::Result<Option<Solution>> {
+    let vars = constraints.width().saturating_sub(1);
+    if costs.len() != vars {
+        anyhow::bail!(
+            "{} costs given for a problem with {} variables",
+            costs.len(),
+            vars
+        );
+    }
+
+    let mut best = None;
+    branch_and_bound(constraints, costs, &mut Vec::new(), &mut best)?;
+    Ok(best)
+}
+
+// Bounds on a single variable. The values are always whole numbers.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Bound {
+    AtMost(usize, Scalar),
+    AtLeast(usize, Scalar),
+}
+
+fn branch_and_bound(
+    constraints: &Matrix,
+    costs: &[Scalar],
+    bounds: &mut Vec<Bound>,
+    best: &mut Option<Solution>,
+) -> anyhow::Result<()> {
+    let Some(relaxed) = solve_relaxation(constraints, costs, bounds)? else {
+        return Ok(());
+    };
+
+    // with integer costs, integer solutions have integer objectives, so the relaxation's
+    // objective can be rounded up before comparing
+    let mut lower_bound = relaxed.objective.clone();
+    if costs.iter().all(|c| c.is_integer()) {
+        lower_bound = lower_bound.ceil();
+    }
+    if best.as_ref().is_some_and(|b| lower_bound >= b.objective) {
+        return Ok(());
+    }
+
+    let Some(var) = relaxed.x.iter().position(|v| !v.is_integer()) else {
+        *best = Some(relaxed);
+        return Ok(());
+    };
+
+    let value = &relaxed.x[var];
+    for bound in [
+        Bound::AtMost(var, value.floor()),
+        Bound::AtLeast(var, value.ceil()),
+    ] {
+        bounds.push(bound);
+        let result = branch_and_bound(constraints, costs, bounds, best);
+        bounds.pop();
+        result?;
+    }
+
+    Ok(())
+}
+
+// Solves the LP relaxation (ie. ignoring integrality) with some extra bounds on the variables.
+// Every bound gets turned into one more equality constraint, with its own slack variable.
+fn solve_relaxation(
+    constraints: &Matrix,
+    costs: &[Scalar],
+    bounds: &[Bound],
+) -> anyhow::Result<Option<Solution>> {
+    let vars = costs.len();
+    let width = vars + bounds.len();
+
+    let mut a: Vec<Vec<Scalar>> = Vec::new();
+    let mut b: Vec<Scalar> = Vec::new();
+    for row in constraints.iter() {
+        let mut r = row[..vars].to_vec();
+        r.resize(width, Scalar::zero());
+        a.push(r);
+        b.push(row[vars].clone());
+    }
+    for (i, bound) in bounds.iter().enumerate() {
+        let mut r = vec![Scalar::zero(); width];
+        let (var, slack, value) = match bound {
+            Bound::AtMost(var, value) => (*var, Scalar::one(), value),
+            Bound::AtLeast(var, value) => (*var, -Scalar::one(), value),
+        };
+        r[var] = Scalar::one();
+        r[vars + i] = slack;
+        a.push(r);
+        b.push(value.clone());
+    }
+
+    let mut all_costs = costs.to_vec();
+    all_costs.resize(width, Scalar::zero());
+
+    Ok(match simplex(a, b, &all_costs) {
+        Lp::Optimal(objective, mut x) => {
+            x.truncate(vars);
+            Some(Solution {
+                objective,
+                x: RowVec::new(x),
+            })
+        }
+        Lp::Infeasible => None,
+        Lp::Unbounded => anyhow::bail!("integer program is unbounded"),
+    })
+}
+
+enum Lp {
+    Optimal(Scalar, Vec<Scalar>),
+    Infeasible,
+    Unbounded,
+}
+
+// Minimises costs · x subject to a x = b and x >= 0, using the two-phase simplex method. Bland's
+// rule is used to choose pivots, so it can't cycle on degenerate problems.
+fn simplex(mut a: Vec<Vec<Scalar>>, mut b: Vec<Scalar>, costs: &[Scalar]) -> Lp {
+    let vars = costs.len();
+
+    // phase 1 starts from the obvious basic solution of x = 0, plus one artificial variable per
+    // row equal to that row's (non-negative) value of b
+    for (row, b) in a.iter_mut().zip(b.iter_mut()) {
+        if b.is_negative() {
+            row.iter_mut().for_each(|v| *v = -&*v);
+            *b = -&*b;
+        }
+    }
+    let rows = a.len();
+    let mut tableau = Tableau {
+        cells: a
+            .into_iter()
+            .zip(b)
+            .enumerate()
+            .map(|(i, (mut row, b))| {
+                row.resize(vars + rows, Scalar::zero());
+                row[vars + i] = Scalar::one();
+                row.push(b);
+                row
+            })
+            .collect(),
+        objective: Vec::new(),
+        basis: (vars..(vars + rows)).collect(),
+    };
+
+    // phase 1: minimise the sum of the artificial variables. If that can't get down to 0, there's
+    // no solution.
+    let mut phase_1_costs = vec![Scalar::zero(); vars];
+    phase_1_costs.resize(vars + rows, Scalar::one());
+    tableau.set_objective(&phase_1_costs);
+    tableau.optimise(vars + rows);
+    if !tableau.objective_value().is_zero() {
+        return Lp::Infeasible;
+    }
+
+    // any artificial variables still in the basis are 0, so swap them out for real variables
+    // where possible. Where it's not, the row was redundant and can just be dropped.
+    let mut row = 0;
+    while row < tableau.cells.len() {
+        if tableau.basis[row] < vars {
+            row += 1;
+        } else if let Some(col) = (0..vars).find(|&c| !tableau.cells[row][c].is_zero()) {
+            tableau.pivot(row, col);
+            row += 1;
+        } else {
+            tableau.cells.remove(row);
+            tableau.basis.remove(row);
+        }
+    }
+    for r in &mut tableau.cells {
+        r.drain(vars..(vars + rows));
+    }
+
+    // phase 2: minimise the real objective, starting from the feasible solution found in phase 1
+    tableau.set_objective(costs);
+    if !tableau.optimise(vars) {
+        return Lp::Unbounded;
+    }
+
+    let mut x = vec![Scalar::zero(); vars];
+    for (row, &var) in tableau.basis.iter().enumerate() {
+        x[var] = tableau.cells[row][vars].clone();
+    }
+    Lp::Optimal(tableau.objective_value(), x)
+}
+
+// A simplex tableau: one row per constraint, with the right-hand side in the last column, plus an
+// objective row of reduced costs. The last entry of the objective row is minus the objective's
+// current value.
+struct Tableau {
+    cells: Vec<Vec<Scalar>>,
+    objective: Vec<Scalar>,
+    basis: Vec<usize>,
+}
+
+impl Tableau {
+    fn rhs(&self) -> usize {
+        self.objective.len() - 1
+    }
+
+    fn objective_value(&self) -> Scalar {
+        -&self.objective[self.rhs()]
+    }
+
+    fn set_objective(&mut self, costs: &[Scalar]) {
+        let width = self.cells.first().map_or(costs.len() + 1, |r| r.len());
+        self.objective = costs.to_vec();
+        self.objective.resize(width, Scalar::zero());
+        for (row, &var) in self.basis.iter().enumerate() {
+            let factor = self.objective[var].clone();
+            if !factor.is_zero() {
+                for (o, c) in self.objective.iter_mut().zip(&self.cells[row]) {
+                    *o -= &factor * c;
+                }
+            }
+        }
+    }
+
+    // Pivots until no column (among the first `cols`) can improve the objective. Returns false if
+    // the objective turns out to be unbounded.
+    fn optimise(&mut self, cols: usize) -> bool {
+        loop {
+            let Some(col) = (0..cols).find(|&c| self.objective[c].is_negative()) else {
+                return true;
+            };
+
+            let rhs = self.rhs();
+            let leaving = (0..self.cells.len())
+                .filter(|&row| self.cells[row][col].is_positive())
+                .min_by_key(|&row| {
+                    (
+                        &self.cells[row][rhs] / &self.cells[row][col],
+                        self.basis[row],
+                    )
+                });
+            let Some(row) = leaving else {
+                return false;
+            };
+
+            self.pivot(row, col);
+        }
+    }
+
+    fn pivot(&mut self, row: usize, col: usize) {
+        let p = self.cells[row][col].clone();
+        self.cells[row].iter_mut().for_each(|v| *v /= &p);
+
+        let pivot_row = self.cells[row].clone();
+        let eliminate = |r: &mut Vec<Scalar>| {
+            let factor = r[col].clone();
+            if !factor.is_zero() {
+                for (v, p) in r.iter_mut().zip(&pivot_row) {
+                    *v -= &factor * p;
+                }
+            }
+        };
+        for (i, r) in self.cells.iter_mut().enumerate() {
+            if i != row {
+                eliminate(r);
+            }
+        }
+        eliminate(&mut self.objective);
+
+        self.basis[row] = col;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+
+    use super::*;
+    use crate::linalg::scalar;
+
+    // [ A | b ] from integer rows
+    fn constraints(rows: &[Vec<i64>]) -> Matrix {
+        Matrix::new(
+            rows.iter()
+                .map(|row| row.iter().map(|&n| scalar(n)).collect())
+                .collect(),
+        )
+    }
+
+    fn ones(n: usize) -> Vec<Scalar> {
+        vec![Scalar::one(); n]
+    }
+
+    fn check(rows: &[Vec<i64>], solution: &Solution) {
+        let vars = rows[0].len() - 1;
+        assert!(
+            solution
+                .x
+                .iter()
+                .all(|x| x.is_integer() && !x.is_negative())
+        );
+        assert_eq!(solution.x.iter().sum::<Scalar>(), solution.objective);
+        for row in rows {
+            let lhs: Scalar = (0..vars).map(|i| scalar(row[i]) * &solution.x[i]).sum();
+            assert_eq!(lhs, scalar(row[vars]), "{:?}: {:?}", rows, solution);
+        }
+    }
+
+    // The fewest presses that add up to b, trying every combination where no button is pressed
+    // more than max times.
+    fn brute_force(rows: &[Vec<i64>], max: i64) -> Option<i64> {
+        let vars = rows[0].len() - 1;
+        let mut presses = vec![0; vars];
+        let mut best = None;
+        loop {
+            let exact = rows
+                .iter()
+                .all(|row| (0..vars).map(|i| row[i] * presses[i]).sum::<i64>() == row[vars]);
+            if exact {
+                let total = presses.iter().sum();
+                best = Some(best.map_or(total, |b: i64| b.min(total)));
+            }
+
+            let Some(i) = presses.iter().position(|&p| p < max) else {
+                return best;
+            };
+            presses[..i].fill(0);
+            presses[i] += 1;
+        }
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        let mut rng = StdRng::seed_from_u64(10);
+        let (mut feasible, mut infeasible) = (0, 0);
+        for _ in 0..300 {
+            let counters = rng.gen_range(1..=4);
+            let buttons = rng.gen_range(1..=5);
+            let max = 6;
+
+            // like day 10: each button adds 1 to some of the counters
+            let mut rows = vec![vec![0; buttons + 1]; counters];
+            for button in 0..buttons {
+                for row in &mut rows {
+                    row[button] = i64::from(rng.gen_bool(0.5));
+                }
+            }
+            for row in &mut rows {
+                row[buttons] = rng.gen_range(0..=max);
+            }
+
+            let expected = brute_force(&rows, max);
+            let solution = minimise(&constraints(&rows), &ones(buttons)).unwrap();
+            assert_eq!(
+                solution.as_ref().map(|s| s.objective.clone()),
+                expected.map(scalar),
+                "{:?}",
+                rows
+            );
+            match solution {
+                Some(solution) => {
+                    check(&rows, &solution);
+                    feasible += 1;
+                }
+                None => infeasible += 1,
+            }
+        }
+        assert!(feasible > 0 && infeasible > 0);
+    }
+
+    #[test]
+    fn artificial_variable_left_in_basis() {
+        // phase 1 ends with the second row's artificial variable still basic (at 0), and x1 has to
+        // be pivoted in to replace it
+        let rows = vec![vec![1, 1, 2], vec![1, 0, 2]];
+        let solution = minimise(&constraints(&rows), &ones(2)).unwrap().unwrap();
+        check(&rows, &solution);
+        assert_eq!(solution.x, RowVec::new(vec![scalar(2), scalar(0)]));
+    }
+
+    #[test]
+    fn redundant_row_dropped() {
+        // the second row is just the first doubled, so phase 1 leaves its artificial variable
+        // basic with nothing to replace it, and the row gets dropped
+        let rows = vec![vec![1, 1, 2], vec![2, 2, 4]];
+        let solution = minimise(&constraints(&rows), &[scalar(2), scalar(1)])
+            .unwrap()
+            .unwrap();
+        assert_eq!(solution.objective, scalar(2));
+        assert_eq!(solution.x, RowVec::new(vec![scalar(0), scalar(2)]));
+    }
+
+    #[test]
+    fn infeasible() {
+        // x0 + x1 = 1 and x0 + x1 = 2 can't both hold
+        let rows = vec![vec![1, 1, 1], vec![1, 1, 2]];
+        assert_eq!(minimise(&constraints(&rows), &ones(2)).unwrap(), None);
+
+        // 2 x0 = 3 has a rational solution, but not an integer one
+        let rows = vec![vec![2, 3]];
+        assert_eq!(minimise(&constraints(&rows), &ones(1)).unwrap(), None);
+    }
+
+    #[test]
+    fn wrong_number_of_costs() {
+        let rows = vec![vec![1, 1, 1]];
+        assert!(minimise(&constraints(&rows), &ones(3)).is_err());
+    }
+}
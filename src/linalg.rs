@@ -0,0 +1,575 @@
+// Exact linear algebra over rationals, shared between days. Not every day uses every operation.
+// see Scalar
+#![allow(clippy::clone_on_copy, clippy::op_ref)]
+
+use std::{
+    cmp::Ordering,
+    fmt,
+    fmt::Formatter,
+    ops::{Deref, DerefMut, Index, IndexMut, Mul, MulAssign},
+};
+
+use num_traits::{One, Zero};
+
+/// The type of every entry. Rational64 is fast, but its numerators and denominators can overflow
+/// while eliminating on bigger systems, so the big-rational feature swaps in arbitrary precision
+/// rationals instead.
+///
+/// Scalar is only Copy without the big-rational feature, so code that has to work either way
+/// clones and borrows where clippy, which only sees the Copy version, thinks it needn't.
+#[cfg(not(feature = "big-rational"))]
+pub type Scalar = num_rational::Rational64;
+#[cfg(feature = "big-rational")]
+pub type Scalar = num_rational::BigRational;
+
+/// An integer as a [`Scalar`].
+// the conversion is only useless without the big-rational feature
+#[allow(clippy::useless_conversion)]
+pub fn scalar(n: i64) -> Scalar {
+    Scalar::from_integer(n.into())
+}
+
+/// A single row of a [`Matrix`] (or a standalone vector).
+#[derive(Clone, Debug, PartialEq)]
+pub struct RowVec(Vec<Scalar>);
+
+impl RowVec {
+    pub fn new(v: Vec<Scalar>) -> Self {
+        Self(v)
+    }
+
+    pub fn zeros(len: usize) -> Self {
+        Self(vec![Scalar::zero(); len])
+    }
+
+    pub fn add_assign(&mut self, rhs: &Self) -> anyhow::Result<()> {
+        if self.0.len() != rhs.0.len() {
+            anyhow::bail!(
+                "addition of RowVecs of different sizes: {} vs {}",
+                self.0.len(),
+                rhs.0.len()
+            );
+        }
+
+        self.0
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, e)| *e += &rhs.0[i]);
+
+        Ok(())
+    }
+
+    /// Scales the row so that its leading entry is 1.
+    pub fn normalize(&mut self) {
+        let Some(leader_col) = self.leader_col() else {
+            return;
+        };
+
+        if !self.0[leader_col].is_one() {
+            let factor = self.0[leader_col].recip();
+            *self *= factor;
+        }
+    }
+
+    pub fn is_zeros(&self) -> bool {
+        self.0.iter().all(|v| v.is_zero())
+    }
+
+    /// The column of the first non-zero entry, if there is one.
+    pub fn leader_col(&self) -> Option<usize> {
+        self.0.iter().position(|e| !e.is_zero())
+    }
+}
+
+impl Deref for RowVec {
+    type Target = Vec<Scalar>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for RowVec {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl MulAssign<Scalar> for RowVec {
+    fn mul_assign(&mut self, rhs: Scalar) {
+        for cell in &mut self.0 {
+            *cell *= &rhs;
+        }
+    }
+}
+
+impl Mul<Scalar> for RowVec {
+    type Output = RowVec;
+
+    fn mul(mut self, rhs: Scalar) -> Self::Output {
+        self *= rhs;
+        self
+    }
+}
+
+impl fmt::Display for RowVec {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "[ ")?;
+        for v in &self.0 {
+            write!(f, "{} ", v)?;
+        }
+        write!(f, "]")?;
+        Ok(())
+    }
+}
+
+/// A dense matrix, stored as a list of rows.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Matrix(Vec<RowVec>);
+
+impl Matrix {
+    pub fn new(m: Vec<Vec<Scalar>>) -> Self {
+        Self(m.into_iter().map(RowVec).collect())
+    }
+
+    pub fn zeros(rows: usize, cols: usize) -> Self {
+        if rows == 0 || cols == 0 {
+            Self(Vec::new())
+        } else {
+            Self(vec![RowVec::zeros(cols); rows])
+        }
+    }
+
+    pub fn matrix_mul(&self, rhs: &Self) -> anyhow::Result<Self> {
+        if (self.0.is_empty() || self.0[0].is_empty()) && (rhs.0.is_empty() || rhs.0[0].len() == 1)
+        {
+            return Ok(Self(Vec::new()));
+        }
+
+        if self.0[0].len() != rhs.0.len() {
+            anyhow::bail!(
+                "multiplication of incompatible matrices: lhs width {} vs rhs height {}",
+                self.0[0].len(),
+                rhs.0.len()
+            );
+        }
+
+        let mut new = Self::zeros(self.0.len(), rhs.0[0].len());
+        for i in 0..rhs.0[0].len() {
+            for j in 0..self.0.len() {
+                for k in 0..self.0[0].len() {
+                    new.0[j][i] += &self.0[j][k] * &rhs.0[k][i];
+                }
+            }
+        }
+        Ok(new)
+    }
+
+    pub fn append_row(&mut self, r: RowVec) {
+        self.0.push(r);
+    }
+
+    pub fn height(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn width(&self) -> usize {
+        if self.0.is_empty() {
+            0
+        } else {
+            self.0[0].len()
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &RowVec> {
+        self.0.iter()
+    }
+}
+
+// Elimination, and the general operations built on it. No day needs these at the moment (day 10
+// solves its systems with ilp instead), but they're kept working by the tests below.
+#[allow(dead_code)]
+impl Matrix {
+    pub fn identity(size: usize) -> Self {
+        let mut m = Self::zeros(size, size);
+        for i in 0..size {
+            m[(i, i)] = Scalar::one();
+        }
+        m
+    }
+
+    /// Puts the matrix into reduced row echelon form, in place.
+    pub fn rref(&mut self) {
+        self.r#ref();
+        for row in 0..self.0.len() {
+            self.eliminate_above_leader(row);
+        }
+        self.leader_sort();
+        self.normalize();
+    }
+
+    /// Puts the matrix into row echelon form, in place.
+    pub fn r#ref(&mut self) {
+        self.leader_sort();
+        for row in 0..self.0.len() {
+            self.eliminate_below_leader(row);
+        }
+        self.leader_sort();
+    }
+
+    /// Sorts the rows by their leader column, with all-zero rows last.
+    pub fn leader_sort(&mut self) {
+        self.0.sort_by(|a, b| {
+            for (ai, bi) in a.iter().zip(b.iter()) {
+                if ai.is_zero() && !bi.is_zero() {
+                    return Ordering::Greater;
+                } else if bi.is_zero() && !ai.is_zero() {
+                    return Ordering::Less;
+                }
+            }
+            Ordering::Equal
+        });
+    }
+
+    fn eliminate_below_leader(&mut self, row: usize) {
+        let Some(leader_col) = self.0[row].leader_col() else {
+            return;
+        };
+
+        for i in (row + 1)..self.0.len() {
+            self.eliminate(row, i, leader_col);
+        }
+    }
+
+    fn eliminate(&mut self, selected_row: usize, other_row: usize, leader_col: usize) {
+        if self.0[other_row][leader_col].is_zero() {
+            return;
+        }
+
+        let factor = -&self.0[other_row][leader_col] / &self.0[selected_row][leader_col];
+        let term = self.0[selected_row].clone() * factor;
+        self.0[other_row].add_assign(&term).unwrap();
+    }
+
+    fn normalize(&mut self) {
+        self.0.iter_mut().for_each(|row| row.normalize());
+    }
+
+    fn eliminate_above_leader(&mut self, row: usize) {
+        let Some(leader_col) = self.0[row].leader_col() else {
+            return;
+        };
+
+        for i in 0..row {
+            self.eliminate(row, i, leader_col);
+        }
+    }
+
+    /// The determinant of a square matrix, by Gaussian elimination.
+    pub fn determinant(&self) -> anyhow::Result<Scalar> {
+        self.check_square("determinant")?;
+
+        let mut m = self.clone();
+        let mut det = Scalar::one();
+        for col in 0..m.width() {
+            let Some(pivot) = (col..m.height()).find(|&row| !m[(row, col)].is_zero()) else {
+                return Ok(Scalar::zero());
+            };
+            if pivot != col {
+                m.0.swap(pivot, col);
+                det = -det;
+            }
+
+            det *= &m[(col, col)];
+            for row in (col + 1)..m.height() {
+                m.eliminate(col, row, col);
+            }
+        }
+        Ok(det)
+    }
+
+    /// The number of linearly independent rows (or equivalently, columns).
+    pub fn rank(&self) -> usize {
+        let mut m = self.clone();
+        m.r#ref();
+        m.iter().filter(|row| !row.is_zeros()).count()
+    }
+
+    /// A basis for the null space: a set of linearly independent vectors x such that `self * x`
+    /// is zero, such that every such x is a linear combination of them. Each basis vector has a 1
+    /// in the position of one free variable, and 0 in the positions of all the others.
+    pub fn null_space(&self) -> Vec<RowVec> {
+        let mut m = self.clone();
+        m.rref();
+
+        let leader_cols: Vec<Option<usize>> = m.iter().map(|row| row.leader_col()).collect();
+        let mut basis = Vec::new();
+        for free_col in 0..m.width() {
+            if leader_cols.contains(&Some(free_col)) {
+                continue;
+            }
+
+            let mut v = RowVec::zeros(m.width());
+            v[free_col] = Scalar::one();
+            for (row, leader_col) in leader_cols.iter().enumerate() {
+                if let Some(leader_col) = leader_col {
+                    v[*leader_col] = -&m[(row, free_col)];
+                }
+            }
+            basis.push(v);
+        }
+        basis
+    }
+
+    /// The inverse of a square matrix, by row-reducing `[ self | I ]` into `[ I | self^-1 ]`.
+    pub fn inverse(&self) -> anyhow::Result<Self> {
+        self.check_square("inverse")?;
+
+        let size = self.height();
+        let identity = Self::identity(size);
+        let mut augmented = Self(
+            self.iter()
+                .zip(identity.iter())
+                .map(|(a, i)| RowVec([a.0.clone(), i.0.clone()].concat()))
+                .collect(),
+        );
+        augmented.rref();
+
+        let (left, right): (Vec<_>, Vec<_>) = augmented
+            .into_iter()
+            .map(|row| (row.0[..size].to_vec(), row.0[size..].to_vec()))
+            .unzip();
+        if Self::new(left) != identity {
+            anyhow::bail!("matrix is singular, so it has no inverse");
+        }
+        Ok(Self::new(right))
+    }
+
+    /// Solves `self * x = b`, returning one solution (with every free variable set to 0) if
+    /// there are any. Every other solution is this one plus some combination of the vectors from
+    /// [`Matrix::null_space`].
+    pub fn solve(&self, b: &[Scalar]) -> anyhow::Result<Option<RowVec>> {
+        if b.len() != self.height() {
+            anyhow::bail!(
+                "can't solve a system with {} equations against {} values",
+                self.height(),
+                b.len()
+            );
+        }
+
+        let width = self.width();
+        let mut augmented = Self(
+            self.iter()
+                .zip(b)
+                .map(|(row, b)| RowVec([row.0.clone(), vec![b.clone()]].concat()))
+                .collect(),
+        );
+        augmented.rref();
+
+        let mut x = RowVec::zeros(width);
+        for row in augmented.iter() {
+            match row.leader_col() {
+                // 0 = b, for some non-zero b
+                Some(leader_col) if leader_col == width => return Ok(None),
+                Some(leader_col) => x[leader_col] = row[width].clone(),
+                None => {}
+            }
+        }
+        Ok(Some(x))
+    }
+
+    pub fn remove_row(&mut self, i: usize) {
+        self.0.remove(i);
+    }
+
+    /// Column c, as an n-by-1 matrix.
+    pub fn get_column_copy(&self, c: usize) -> Self {
+        Self::new(self.iter().map(|r| vec![r[c].clone()]).collect())
+    }
+
+    fn check_square(&self, operation: &str) -> anyhow::Result<()> {
+        if self.height() != self.width() {
+            anyhow::bail!(
+                "{} of a non-square matrix: height {} vs width {}",
+                operation,
+                self.height(),
+                self.width()
+            );
+        }
+        Ok(())
+    }
+}
+
+impl MulAssign<Scalar> for Matrix {
+    fn mul_assign(&mut self, rhs: Scalar) {
+        for row in &mut self.0 {
+            *row *= rhs.clone();
+        }
+    }
+}
+
+impl Mul<Scalar> for Matrix {
+    type Output = Self;
+
+    fn mul(mut self, rhs: Scalar) -> Self::Output {
+        self *= rhs;
+        self
+    }
+}
+
+impl Index<usize> for Matrix {
+    type Output = RowVec;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.0[index]
+    }
+}
+
+impl IndexMut<usize> for Matrix {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        &mut self.0[index]
+    }
+}
+
+impl Index<(usize, usize)> for Matrix {
+    type Output = Scalar;
+
+    fn index(&self, index: (usize, usize)) -> &Self::Output {
+        &self.0[index.0][index.1]
+    }
+}
+
+impl IndexMut<(usize, usize)> for Matrix {
+    fn index_mut(&mut self, index: (usize, usize)) -> &mut Self::Output {
+        &mut self.0[index.0][index.1]
+    }
+}
+
+impl IntoIterator for Matrix {
+    type Item = RowVec;
+    type IntoIter = std::vec::IntoIter<Self::Item>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl fmt::Display for Matrix {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "[ ")?;
+        let mut first = true;
+        for v in &self.0 {
+            if !first {
+                write!(f, "\n  ")?;
+            }
+            first = false;
+            write!(f, "{} ", v)?;
+        }
+        write!(f, "]")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matrix(rows: &[&[i64]]) -> Matrix {
+        Matrix::new(
+            rows.iter()
+                .map(|row| row.iter().map(|&n| scalar(n)).collect())
+                .collect(),
+        )
+    }
+
+    fn scalars(v: &[i64]) -> Vec<Scalar> {
+        v.iter().map(|&n| scalar(n)).collect()
+    }
+
+    // m * x, with x as a column vector
+    fn apply(m: &Matrix, x: &RowVec) -> Vec<Scalar> {
+        let column = Matrix::new(x.iter().map(|e| vec![e.clone()]).collect());
+        m.matrix_mul(&column)
+            .unwrap()
+            .into_iter()
+            .map(|row| row[0].clone())
+            .collect()
+    }
+
+    #[test]
+    fn determinant() {
+        let m = matrix(&[&[0, 2, 1], &[3, 1, 4], &[1, 0, 2]]);
+        assert_eq!(m.determinant().unwrap(), scalar(-5));
+
+        // the first column's pivot is in the second row, so elimination has to swap rows too
+        let swapped = matrix(&[&[3, 1, 4], &[0, 2, 1], &[1, 0, 2]]);
+        assert_eq!(swapped.determinant().unwrap(), scalar(5));
+
+        let singular = matrix(&[&[1, 2, 3], &[2, 4, 6], &[0, 1, 1]]);
+        assert_eq!(singular.determinant().unwrap(), scalar(0));
+
+        assert!(matrix(&[&[1, 2, 3], &[4, 5, 6]]).determinant().is_err());
+    }
+
+    #[test]
+    fn rank() {
+        assert_eq!(Matrix::identity(3).rank(), 3);
+        assert_eq!(matrix(&[&[0, 0], &[0, 0]]).rank(), 0);
+
+        let deficient = matrix(&[&[1, 2, 3], &[2, 4, 6], &[1, 0, 1], &[2, 2, 4]]);
+        assert_eq!(deficient.rank(), 2);
+    }
+
+    #[test]
+    fn null_space() {
+        for m in [
+            // the first column is all zeros, so it's free without being to the right of a leader
+            matrix(&[&[0, 1, 2, 3], &[0, 2, 4, 7]]),
+            matrix(&[&[1, 2, 0, -1], &[2, 4, 1, 1], &[3, 6, 1, 0]]),
+            matrix(&[&[2, 1], &[5, 3]]),
+        ] {
+            let basis = m.null_space();
+            assert_eq!(basis.len(), m.width() - m.rank());
+            for v in &basis {
+                assert!(!v.is_zeros());
+                assert!(apply(&m, v).iter().all(|e| e.is_zero()), "{} * {}", m, v);
+            }
+        }
+    }
+
+    #[test]
+    fn inverse() {
+        let m = matrix(&[&[2, 1], &[5, 3]]);
+        assert_eq!(m.inverse().unwrap(), matrix(&[&[3, -1], &[-5, 2]]));
+
+        // needs a row swap, and has fractions in its inverse
+        let m = matrix(&[&[0, 1, 2], &[1, 0, 3], &[4, -3, 8]]);
+        let inverse = m.inverse().unwrap();
+        assert_eq!(m.matrix_mul(&inverse).unwrap(), Matrix::identity(3));
+        assert_eq!(inverse.matrix_mul(&m).unwrap(), Matrix::identity(3));
+
+        assert!(matrix(&[&[1, 2], &[2, 4]]).inverse().is_err());
+        assert!(matrix(&[&[1, 2, 3]]).inverse().is_err());
+    }
+
+    #[test]
+    fn solve() {
+        let m = matrix(&[&[2, 1], &[1, 3]]);
+        let b = scalars(&[3, 5]);
+        let x = m.solve(&b).unwrap().unwrap();
+        assert_eq!(apply(&m, &x), b);
+
+        // underdetermined, so the free variable is set to 0
+        let m = matrix(&[&[1, 1, 1], &[0, 1, 2]]);
+        let b = scalars(&[6, 5]);
+        let x = m.solve(&b).unwrap().unwrap();
+        assert_eq!(apply(&m, &x), b);
+        assert!(x[2].is_zero());
+
+        let m = matrix(&[&[1, 2], &[2, 4]]);
+        assert_eq!(m.solve(&scalars(&[1, 3])).unwrap(), None);
+        assert!(m.solve(&scalars(&[1, 2])).unwrap().is_some());
+
+        assert!(m.solve(&scalars(&[1])).is_err());
+    }
+}
@@ -1,15 +1,62 @@
 mod days;
+mod ilp;
+mod linalg;
 
-use clap::Parser;
+use clap::{ArgAction, Parser, Subcommand};
+use tracing::Level;
 
 /// Advent of Code 2025
 #[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
+#[command(version, about, long_about = None, subcommand_negates_reqs = true)]
 struct Cli {
     /// The day to run.
-    day: u8,
+    #[arg(required = true)]
+    day: Option<u8>,
+
+    /// Log debugging output to stderr. Repeat (-vv) for even more detail.
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    verbose: u8,
+
+    #[command(flatten)]
+    options: days::Options,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Answer a question about a day's input, instead of solving the day's puzzle.
+    Query {
+        /// The day to query.
+        day: u8,
+
+        #[command(flatten)]
+        query: days::QueryArgs,
+    },
 }
 
 fn main() {
-    days::run(Cli::parse().day);
+    let cli = Cli::parse();
+
+    // logs go to stderr, so they never get mixed up with the answers
+    let level = match cli.verbose {
+        0 => Level::WARN,
+        1 => Level::DEBUG,
+        _ => Level::TRACE,
+    };
+    tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_writer(std::io::stderr)
+        .without_time()
+        .init();
+
+    match cli.command {
+        Some(Command::Query { day, query }) => {
+            let mut options = cli.options;
+            options.query = Some(query);
+            days::run(day, &options);
+        }
+        None => days::run(cli.day.unwrap(), &cli.options),
+    }
 }